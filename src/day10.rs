@@ -1,43 +1,651 @@
 use crate::utils::read_lines;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 
-pub fn run() {
-    let lines = read_lines("in/day10.in").unwrap();
-    let mut register = 1;
+/// Register file. Registers are named by a single character and read as 0
+/// until written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Registers(BTreeMap<char, i64>);
 
-    let mut register_values = vec![];
-    register_values.push(1);
-
-    for line in lines {
-        let l = line.unwrap();
-        scan!(&l;
-          ("noop") => {
-            register_values.push(register);
-          },
-          ("addx", let delta: i32) => {
-            register_values.push(register);
-            register_values.push(register);
-            register += delta;
-          },
+impl Registers {
+    pub fn get(&self, name: char) -> i64 {
+        self.0.get(&name).cloned().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, name: char, value: i64) {
+        self.0.insert(name, value);
+    }
+}
+
+/// Behaviour of an instruction defined with `InstructionSet::define_with`:
+/// applies the operands to the registers. On overflow it must return `None`
+/// and leave the registers unchanged.
+pub type Semantics = fn(&mut Registers, &[i64]) -> Option<()>;
+
+/// A decoded instruction defined with `InstructionSet::define_with`.
+#[derive(Clone)]
+pub struct Custom {
+    pub mnemonic: String,
+    pub operands: Vec<i64>,
+    semantics: Semantics,
+}
+
+impl fmt::Debug for Custom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Custom")
+            .field("mnemonic", &self.mnemonic)
+            .field("operands", &self.operands)
+            .finish()
+    }
+}
+
+/// Mnemonics are unique within an instruction set, so the behaviour is not
+/// compared.
+impl PartialEq for Custom {
+    fn eq(&self, other: &Custom) -> bool {
+        self.mnemonic == other.mnemonic && self.operands == other.operands
+    }
+}
+
+impl Eq for Custom {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Noop,
+    /// Adds a value to a register.
+    Add(char, i64),
+    /// Overwrites a register.
+    Set(char, i64),
+    /// Multiplies a register by a value.
+    Mul(char, i64),
+    Custom(Custom),
+}
+
+impl Instruction {
+    /// Applies the instruction. Arithmetic is checked: on overflow the
+    /// registers are left unchanged and `None` is returned.
+    fn execute(&self, registers: &mut Registers) -> Option<()> {
+        match *self {
+            Instruction::Noop => {}
+            Instruction::Add(name, value) => {
+                registers.set(name, registers.get(name).checked_add(value)?)
+            }
+            Instruction::Set(name, value) => registers.set(name, value),
+            Instruction::Mul(name, value) => {
+                registers.set(name, registers.get(name).checked_mul(value)?)
+            }
+            Instruction::Custom(ref custom) => (custom.semantics)(registers, &custom.operands)?,
+        }
+        Some(())
+    }
+}
+
+/// An instruction together with the number of cycles it takes. Its effect is
+/// visible from the cycle after the last one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decoded {
+    pub instruction: Instruction,
+    pub cycles: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownInstruction(String),
+    InvalidOperands(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnknownInstruction(line) => write!(f, "unknown instruction: {}", line),
+            ParseError::InvalidOperands(line) => write!(f, "invalid operands: {}", line),
+        }
+    }
+}
+
+/// Turns the operands following a mnemonic into an instruction.
+pub type OperandParser = fn(&[&str]) -> Option<Instruction>;
+
+enum Definition {
+    /// Decodes to one of the built-in instructions.
+    Builtin(OperandParser),
+    /// Takes `arity` integer operands and runs its own semantics.
+    Custom(usize, Semantics),
+}
+
+/// Mnemonics the parser understands, with their cycle costs.
+pub struct InstructionSet {
+    definitions: HashMap<String, (u32, Definition)>,
+}
+
+impl Default for InstructionSet {
+    /// The handheld's instructions: `noop` (1 cycle) and `addx V` (2 cycles).
+    fn default() -> InstructionSet {
+        let mut set = InstructionSet::new();
+        set.define("noop", 1, |operands| {
+            operands.is_empty().then_some(Instruction::Noop)
+        });
+        set.define("addx", 2, |operands| match operands {
+            [value] => Some(Instruction::Add('x', value.parse().ok()?)),
+            _ => None,
+        });
+        set
+    }
+}
+
+impl InstructionSet {
+    pub fn new() -> InstructionSet {
+        InstructionSet {
+            definitions: HashMap::new(),
+        }
+    }
+
+    /// Adds or replaces a mnemonic that decodes to one of the built-in
+    /// instructions. Use `define_with` for new behaviour.
+    /// ```
+    /// use aoc::day10::{Decoded, Instruction, InstructionSet};
+    /// let mut set = InstructionSet::default();
+    /// set.define("muly", 3, |operands| match operands {
+    ///     [value] => Some(Instruction::Mul('y', value.parse().ok()?)),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(
+    ///     set.parse("muly -2"),
+    ///     Ok(Decoded{instruction: Instruction::Mul('y', -2), cycles: 3})
+    /// );
+    /// assert!(set.parse("muly").is_err());
+    /// assert!(set.parse("jmp 2").is_err());
+    /// ```
+    pub fn define(&mut self, mnemonic: &str, cycles: u32, parser: OperandParser) -> &mut Self {
+        assert!(cycles > 0, "Instructions take at least one cycle");
+        self.definitions
+            .insert(mnemonic.to_owned(), (cycles, Definition::Builtin(parser)));
+        self
+    }
+
+    /// Adds or replaces a mnemonic with its own behaviour, taking `arity`
+    /// integer operands.
+    /// ```
+    /// use aoc::day10::{Cpu, InstructionSet, Overflow};
+    /// let mut set = InstructionSet::default();
+    /// set.define_with("powx", 3, 1, |registers, operands| {
+    ///     let x = registers.get('x').checked_pow(u32::try_from(operands[0]).ok()?)?;
+    ///     registers.set('x', x);
+    ///     Some(())
+    /// });
+    /// assert!(set.parse("powx").is_err());
+    /// assert!(set.parse("powx two").is_err());
+    ///
+    /// let program = ["addx 2", "powx 10"].iter().map(|l| set.parse(l).unwrap()).collect();
+    /// let mut cpu = Cpu::new(program);
+    /// cpu.run().unwrap();
+    /// assert_eq!((cpu.cycle(), cpu.registers().get('x')), (5, 3i64.pow(10)));
+    ///
+    /// let overflowing = set.parse("powx 64").unwrap();
+    /// let mut cpu = Cpu::new(vec![set.parse("addx 2").unwrap(), overflowing.clone()]);
+    /// assert_eq!(cpu.run(), Err(Overflow{cycle: 5, instruction: overflowing.instruction}));
+    /// ```
+    pub fn define_with(
+        &mut self,
+        mnemonic: &str,
+        cycles: u32,
+        arity: usize,
+        semantics: Semantics,
+    ) -> &mut Self {
+        assert!(cycles > 0, "Instructions take at least one cycle");
+        self.definitions.insert(
+            mnemonic.to_owned(),
+            (cycles, Definition::Custom(arity, semantics)),
+        );
+        self
+    }
+
+    pub fn parse(&self, line: &str) -> Result<Decoded, ParseError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (mnemonic, operands) = words
+            .split_first()
+            .ok_or_else(|| ParseError::UnknownInstruction(line.to_owned()))?;
+        let (cycles, definition) = self
+            .definitions
+            .get(*mnemonic)
+            .ok_or_else(|| ParseError::UnknownInstruction(line.to_owned()))?;
+        let invalid = || ParseError::InvalidOperands(line.to_owned());
+        let instruction = match definition {
+            Definition::Builtin(parser) => parser(operands).ok_or_else(invalid)?,
+            Definition::Custom(arity, semantics) => {
+                if operands.len() != *arity {
+                    return Err(invalid());
+                }
+                Instruction::Custom(Custom {
+                    mnemonic: mnemonic.to_string(),
+                    operands: operands
+                        .iter()
+                        .map(|operand| operand.parse().map_err(|_| invalid()))
+                        .collect::<Result<_, _>>()?,
+                    semantics: *semantics,
+                })
+            }
+        };
+        Ok(Decoded {
+            instruction,
+            cycles: *cycles,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// Paused before the given cycle starts.
+    Breakpoint(usize),
+    /// The program has finished.
+    Halted,
+}
+
+/// A register overflowed when `instruction` completed in `cycle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Overflow {
+    pub cycle: usize,
+    pub instruction: Instruction,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "register overflow in cycle {}: {:?}",
+            self.cycle, self.instruction
         )
-        .unwrap();
     }
+}
 
-    let part1 = [20, 60, 100, 140, 180, 220]
-        .iter()
-        .map(|idx| *idx as i64 * register_values[*idx] as i64)
-        .sum::<i64>();
-    println!("Day 10, part 1 {}", part1);
+/// Called at the start of each cycle with the cycle number (from 1) and the
+/// register values during that cycle.
+pub type Observer<'a> = Box<dyn FnMut(usize, &Registers) + 'a>;
 
-    for y in 0..6 {
-        for x in 0..40 {
-            let cycle = y * 40 + x;
-            let sprite_pos = register_values[cycle + 1];
-            if (cycle as i32 % 40 - sprite_pos).abs() <= 1 {
-                print!("#");
-            } else {
-                print!(".");
+/// Cycle-accurate CPU. The `x` register starts at 1.
+pub struct Cpu<'a> {
+    program: Vec<Decoded>,
+    pc: usize,
+    /// Cycles already spent on `program[pc]`.
+    progress: u32,
+    /// Number of completed cycles.
+    cycle: usize,
+    registers: Registers,
+    breakpoints: HashSet<usize>,
+    paused_at: Option<usize>,
+    /// Set once an instruction overflows; the CPU stops there for good.
+    fault: Option<Overflow>,
+    observers: Vec<Observer<'a>>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: Vec<Decoded>) -> Cpu<'a> {
+        let mut registers = Registers::default();
+        registers.set('x', 1);
+        Cpu {
+            program,
+            pc: 0,
+            progress: 0,
+            cycle: 0,
+            registers,
+            breakpoints: HashSet::new(),
+            paused_at: None,
+            fault: None,
+            observers: vec![],
+        }
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Number of cycles completed so far.
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    /// Makes `run` pause before `cycle` starts, so `registers` shows the values
+    /// during that cycle.
+    pub fn add_breakpoint(&mut self, cycle: usize) {
+        self.breakpoints.insert(cycle);
+    }
+
+    pub fn add_observer<F: FnMut(usize, &Registers) + 'a>(&mut self, observer: F) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Executes a single cycle. Returns its number, or `None` once halted.
+    /// After an overflow every call returns the same error.
+    pub fn step(&mut self) -> Result<Option<usize>, Overflow> {
+        if let Some(fault) = &self.fault {
+            return Err(fault.clone());
+        }
+        let current = match self.program.get(self.pc) {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+        self.cycle += 1;
+        for observer in self.observers.iter_mut() {
+            observer(self.cycle, &self.registers);
+        }
+        self.progress += 1;
+        if self.progress == current.cycles {
+            if current.instruction.execute(&mut self.registers).is_none() {
+                let fault = Overflow {
+                    cycle: self.cycle,
+                    instruction: current.instruction.clone(),
+                };
+                self.fault = Some(fault.clone());
+                return Err(fault);
+            }
+            self.pc += 1;
+            self.progress = 0;
+        }
+        Ok(Some(self.cycle))
+    }
+
+    /// Runs until the next breakpoint or the end of the program.
+    /// ```
+    /// use aoc::day10::{Cpu, Instruction, InstructionSet, Overflow, Stop};
+    /// let set = InstructionSet::default();
+    /// let program = ["noop", "addx 3", "addx -5"].iter().map(|l| set.parse(l).unwrap()).collect();
+    /// let mut seen = vec![];
+    /// let mut cpu = Cpu::new(program);
+    /// cpu.add_observer(|cycle, registers| seen.push((cycle, registers.get('x'))));
+    /// cpu.add_breakpoint(4);
+    /// assert_eq!(cpu.run(), Ok(Stop::Breakpoint(4)));
+    /// assert_eq!(cpu.registers().get('x'), 4);
+    /// assert_eq!(cpu.run(), Ok(Stop::Halted));
+    /// assert_eq!(cpu.registers().get('x'), -1);
+    /// drop(cpu);
+    /// assert_eq!(seen, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+    ///
+    /// let mut set = InstructionSet::default();
+    /// set.define("mulx", 1, |operands| match operands {
+    ///     [value] => Some(Instruction::Mul('x', value.parse().ok()?)),
+    ///     _ => None,
+    /// });
+    /// let program = vec![set.parse("mulx 4294967296").unwrap(); 3];
+    /// let mut cpu = Cpu::new(program);
+    /// let overflow = Overflow{cycle: 2, instruction: Instruction::Mul('x', 1 << 32)};
+    /// assert_eq!(cpu.run(), Err(overflow.clone()));
+    /// assert_eq!(cpu.registers().get('x'), 1 << 32);
+    /// assert_eq!(cpu.step(), Err(overflow));
+    /// ```
+    pub fn run(&mut self) -> Result<Stop, Overflow> {
+        loop {
+            if let Some(fault) = &self.fault {
+                return Err(fault.clone());
             }
+            if self.halted() {
+                return Ok(Stop::Halted);
+            }
+            let next = self.cycle + 1;
+            if self.breakpoints.contains(&next) && self.paused_at != Some(next) {
+                self.paused_at = Some(next);
+                return Ok(Stop::Breakpoint(next));
+            }
+            self.step()?;
         }
-        println!();
     }
 }
+
+/// Monochrome image, row-major, `true` for a lit pixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Bitmap {
+    pub fn new(width: usize, height: usize) -> Bitmap {
+        Bitmap {
+            width,
+            height,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, lit: bool) {
+        self.pixels[y * self.width + x] = lit;
+    }
+}
+
+impl Bitmap {
+    /// Plain PBM (`P1`) image, readable by most image tools.
+    /// ```
+    /// use aoc::day10::Bitmap;
+    /// let mut bitmap = Bitmap::new(3, 2);
+    /// bitmap.set(1, 0, true);
+    /// assert_eq!(bitmap.to_pbm(), "P1\n3 2\n0 1 0\n0 0 0\n");
+    /// ```
+    pub fn to_pbm(&self) -> String {
+        let mut pbm = format!("P1\n{} {}\n", self.width, self.height);
+        for y in 0..self.height {
+            let row: Vec<&str> = (0..self.width)
+                .map(|x| if self.get(x, y) { "1" } else { "0" })
+                .collect();
+            pbm += &row.join(" ");
+            pbm.push('\n');
+        }
+        pbm
+    }
+}
+
+impl fmt::Display for Bitmap {
+    /// Rows of `#` and `.`, as the puzzle draws the CRT.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                write!(f, "{}", if self.get(x, y) { '#' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// CRT drawn one pixel per cycle, row by row, while a horizontal sprite
+/// centred on `x` lights the pixels it covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crt {
+    pub width: usize,
+    pub height: usize,
+    pub sprite_width: usize,
+    /// Cycles (from 1) at which the signal strength is sampled.
+    pub sample_cycles: Vec<usize>,
+}
+
+impl Default for Crt {
+    /// The handheld's 40×6 screen, 3-pixel sprite and samples every 40
+    /// cycles from cycle 20.
+    fn default() -> Crt {
+        Crt {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+            sample_cycles: Crt::periodic_schedule(20, 40, 6),
+        }
+    }
+}
+
+impl Crt {
+    /// `count` cycles starting at `first`, `period` apart.
+    pub fn periodic_schedule(first: usize, period: usize, count: usize) -> Vec<usize> {
+        (0..count).map(|i| first + i * period).collect()
+    }
+
+    /// Sum of cycle number times `x` over the sampled cycles, given `x`
+    /// during each cycle. Cycles past the end of the program are skipped.
+    /// ```
+    /// use aoc::day10::Crt;
+    /// let crt = Crt{sample_cycles: vec![2, 3, 10], ..Crt::default()};
+    /// assert_eq!(crt.signal_strength(&[1, 5, -2]), 2 * 5 + 3 * -2);
+    /// ```
+    pub fn signal_strength(&self, x_values: &[i64]) -> i64 {
+        self.sample_cycles
+            .iter()
+            .filter_map(|&cycle| Some(cycle as i64 * x_values.get(cycle.checked_sub(1)?)?))
+            .sum()
+    }
+
+    fn lit(&self, column: usize, sprite_pos: i64) -> bool {
+        let left = sprite_pos - (self.sprite_width as i64 - 1) / 2;
+        let column = column as i64;
+        left <= column && column < left + self.sprite_width as i64
+    }
+
+    /// One frame per `width × height` cycles, given `x` during each cycle. A
+    /// final partial frame is left dark where the program ended.
+    /// ```
+    /// use aoc::day10::Crt;
+    /// let crt = Crt{width: 4, height: 1, sprite_width: 2, ..Crt::default()};
+    /// let frames = crt.render(&[0, 2, 2, 0, 3]);
+    /// assert_eq!(frames.len(), 2);
+    /// assert_eq!(frames[0].to_string(), "#.#.\n");
+    /// assert_eq!(frames[1].to_string(), "....\n");
+    /// ```
+    pub fn render(&self, x_values: &[i64]) -> Vec<Bitmap> {
+        let frame_size = self.width * self.height;
+        assert!(frame_size > 0, "CRT must have at least one pixel");
+        x_values
+            .chunks(frame_size)
+            .map(|cycles| {
+                let mut frame = Bitmap::new(self.width, self.height);
+                for (pixel, &sprite_pos) in cycles.iter().enumerate() {
+                    let (x, y) = (pixel % self.width, pixel / self.width);
+                    frame.set(x, y, self.lit(x, sprite_pos));
+                }
+                frame
+            })
+            .collect()
+    }
+}
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+
+/// The CRT's letters, each drawn as 6 rows of 4 pixels.
+const FONT: [(char, &str); 17] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    /// The bitmap is not one row of glyphs.
+    WrongHeight(usize),
+    /// Glyph positions (from 0) that are not in the font, each with its
+    /// `#`/`.` rows.
+    UnrecognisedGlyphs(Vec<(usize, String)>),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::WrongHeight(height) => {
+                write!(f, "expected {} rows, got {}", GLYPH_HEIGHT, height)
+            }
+            OcrError::UnrecognisedGlyphs(glyphs) => {
+                write!(f, "unrecognised glyphs at positions ")?;
+                let positions: Vec<String> = glyphs.iter().map(|(i, _)| i.to_string()).collect();
+                write!(f, "{}", positions.join(", "))
+            }
+        }
+    }
+}
+
+/// Reads the letters on a CRT frame. Glyphs are 4 pixels wide with one blank
+/// column between them; a blank glyph reads as a space.
+/// ```
+/// use aoc::day10::{decode, Bitmap, OcrError};
+/// let rows = [
+///     "#..#...##.",
+///     "#..#...#..",
+///     "####...#..",
+///     "#..#...#..",
+///     "#..#...#..",
+///     "#..#..###.",
+/// ];
+/// let mut frame = Bitmap::new(10, 6);
+/// for (y, row) in rows.iter().enumerate() {
+///     for (x, c) in row.chars().enumerate() {
+///         frame.set(x, y, c == '#');
+///     }
+/// }
+/// assert_eq!(
+///     decode(&frame),
+///     Err(OcrError::UnrecognisedGlyphs(vec![(1, "..##..#...#...#...#..###".to_owned())]))
+/// );
+/// frame.set(6, 0, true);
+/// assert_eq!(decode(&frame), Ok("HI".to_owned()));
+/// ```
+pub fn decode(frame: &Bitmap) -> Result<String, OcrError> {
+    if frame.height != GLYPH_HEIGHT {
+        return Err(OcrError::WrongHeight(frame.height));
+    }
+    let mut text = String::new();
+    let mut unrecognised = vec![];
+    for position in 0..(frame.width + 1) / (GLYPH_WIDTH + 1) {
+        let left = position * (GLYPH_WIDTH + 1);
+        let glyph: String = (0..GLYPH_HEIGHT)
+            .flat_map(|y| (left..left + GLYPH_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| if frame.get(x, y) { '#' } else { '.' })
+            .collect();
+        match FONT.iter().find(|(_, pixels)| *pixels == glyph) {
+            Some((letter, _)) => text.push(*letter),
+            None if !glyph.contains('#') => text.push(' '),
+            None => unrecognised.push((position, glyph)),
+        }
+    }
+    if unrecognised.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnrecognisedGlyphs(unrecognised))
+    }
+}
+
+pub fn run() {
+    let lines = read_lines("in/day10.in").unwrap();
+    let instruction_set = InstructionSet::default();
+    let program: Vec<Decoded> = lines
+        .map(|line| instruction_set.parse(&line.unwrap()).unwrap())
+        .collect();
+
+    let mut register_values = vec![];
+    let mut cpu = Cpu::new(program);
+    cpu.add_observer(|_, registers| register_values.push(registers.get('x')));
+    cpu.run().unwrap();
+    drop(cpu);
+
+    let crt = Crt::default();
+    println!("Day 10, part 1 {}", crt.signal_strength(&register_values));
+
+    let frame = &crt.render(&register_values)[0];
+    print!("{}", frame);
+    println!("Day 10, part 2 {}", decode(frame).unwrap());
+}
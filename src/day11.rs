@@ -1,18 +1,565 @@
 use crate::utils::read_lines;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
-enum Op {
-    Add(u64),
-    Mul(u64),
-    Square,
+/// Right-hand side of a monkey's `Operation: new = ...` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Old,
+    Const(u64),
+    Add(Box<Op>, Box<Op>),
+    Sub(Box<Op>, Box<Op>),
+    Mul(Box<Op>, Box<Op>),
+    Div(Box<Op>, Box<Op>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset in the expression where parsing failed.
+    pub position: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {}: {}", self.position, self.reason)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EvalError {
+    Overflow,
+    /// Subtraction below zero.
+    Underflow,
+    DivisionByZero,
+    /// The arithmetic backend cannot perform this operation.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::Overflow => write!(f, "worry level overflow"),
+            EvalError::Underflow => write!(f, "worry level below zero"),
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::Unsupported(operation) => write!(f, "{} is not supported", operation),
+        }
+    }
+}
+
+/// Recursive-descent parser over `expr := term (('+' | '-') term)*`,
+/// `term := factor (('*' | '/') factor)*`,
+/// `factor := number | 'old' | '(' expr ')'`.
+struct OpParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> OpParser<'a> {
+    fn error<T>(&self, reason: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position,
+            reason: reason.to_owned(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+        self.input[self.position..].chars().next()
+    }
+
+    fn expr(&mut self) -> Result<Op, ParseError> {
+        let mut lhs = self.term()?;
+        while let Some(c @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let rhs = Box::new(self.term()?);
+            lhs = match c {
+                '+' => Op::Add(Box::new(lhs), rhs),
+                _ => Op::Sub(Box::new(lhs), rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Op, ParseError> {
+        let mut lhs = self.factor()?;
+        while let Some(c @ ('*' | '/')) = self.peek() {
+            self.position += 1;
+            let rhs = Box::new(self.factor()?);
+            lhs = match c {
+                '*' => Op::Mul(Box::new(lhs), rhs),
+                _ => Op::Div(Box::new(lhs), rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn factor(&mut self) -> Result<Op, ParseError> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let inner = self.expr()?;
+                if self.peek() != Some(')') {
+                    return self.error("expected ')'");
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let rest = &self.input[self.position..];
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                match rest[..len].parse() {
+                    Ok(value) => {
+                        self.position += len;
+                        Ok(Op::Const(value))
+                    }
+                    Err(_) => self.error("constant too large"),
+                }
+            }
+            Some(_) if self.input[self.position..].starts_with("old") => {
+                self.position += 3;
+                Ok(Op::Old)
+            }
+            Some(_) => self.error("expected a number, 'old' or '('"),
+            None => self.error("unexpected end of expression"),
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = ParseError;
+
+    /// ```
+    /// use aoc::day11::Op;
+    /// let op: Op = "(old + 3) * old - 10 / 2".parse().unwrap();
+    /// assert_eq!(op.eval(4), Ok(23));
+    /// assert_eq!("old * ".parse::<Op>().unwrap_err().position, 6);
+    /// assert_eq!("old old".parse::<Op>().unwrap_err().position, 4);
+    /// ```
+    fn from_str(s: &str) -> Result<Op, ParseError> {
+        let mut parser = OpParser {
+            input: s,
+            position: 0,
+        };
+        let op = parser.expr()?;
+        if parser.peek().is_some() {
+            return parser.error("unexpected trailing input");
+        }
+        Ok(op)
+    }
+}
+
+/// How worry levels are represented and computed.
+pub trait Arithmetic {
+    type Value: Clone + fmt::Debug;
+
+    fn constant(&self, value: u64) -> Self::Value;
+    fn add(&self, lhs: &Self::Value, rhs: &Self::Value) -> Result<Self::Value, EvalError>;
+    fn sub(&self, lhs: &Self::Value, rhs: &Self::Value) -> Result<Self::Value, EvalError>;
+    fn mul(&self, lhs: &Self::Value, rhs: &Self::Value) -> Result<Self::Value, EvalError>;
+    fn div(&self, lhs: &Self::Value, rhs: &Self::Value) -> Result<Self::Value, EvalError>;
+    fn is_divisible_by(&self, value: &Self::Value, divisor: u64) -> bool;
+}
+
+/// `u64` that reports overflow instead of wrapping.
+pub struct CheckedU64;
+
+impl Arithmetic for CheckedU64 {
+    type Value = u64;
+
+    fn constant(&self, value: u64) -> u64 {
+        value
+    }
+
+    fn add(&self, lhs: &u64, rhs: &u64) -> Result<u64, EvalError> {
+        lhs.checked_add(*rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn sub(&self, lhs: &u64, rhs: &u64) -> Result<u64, EvalError> {
+        lhs.checked_sub(*rhs).ok_or(EvalError::Underflow)
+    }
+
+    fn mul(&self, lhs: &u64, rhs: &u64) -> Result<u64, EvalError> {
+        lhs.checked_mul(*rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn div(&self, lhs: &u64, rhs: &u64) -> Result<u64, EvalError> {
+        lhs.checked_div(*rhs).ok_or(EvalError::DivisionByZero)
+    }
+
+    fn is_divisible_by(&self, value: &u64, divisor: u64) -> bool {
+        value.is_multiple_of(divisor)
+    }
+}
+
+/// `u128` that reports overflow instead of wrapping.
+pub struct CheckedU128;
+
+impl Arithmetic for CheckedU128 {
+    type Value = u128;
+
+    fn constant(&self, value: u64) -> u128 {
+        value as u128
+    }
+
+    fn add(&self, lhs: &u128, rhs: &u128) -> Result<u128, EvalError> {
+        lhs.checked_add(*rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn sub(&self, lhs: &u128, rhs: &u128) -> Result<u128, EvalError> {
+        lhs.checked_sub(*rhs).ok_or(EvalError::Underflow)
+    }
+
+    fn mul(&self, lhs: &u128, rhs: &u128) -> Result<u128, EvalError> {
+        lhs.checked_mul(*rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn div(&self, lhs: &u128, rhs: &u128) -> Result<u128, EvalError> {
+        lhs.checked_div(*rhs).ok_or(EvalError::DivisionByZero)
+    }
+
+    fn is_divisible_by(&self, value: &u128, divisor: u64) -> bool {
+        value.is_multiple_of(divisor as u128)
+    }
+}
+
+/// Arbitrary-precision natural number, stored as little-endian base 2^32 limbs
+/// without trailing zeros.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Natural {
+    limbs: Vec<u32>,
+}
+
+impl Natural {
+    pub fn zero() -> Natural {
+        Natural { limbs: vec![] }
+    }
+
+    fn normalized(mut limbs: Vec<u32>) -> Natural {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Natural { limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn bit_len(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, idx: usize) -> bool {
+        self.limbs
+            .get(idx / 32)
+            .is_some_and(|limb| limb >> (idx % 32) & 1 == 1)
+    }
+
+    fn shl1_with(&self, low_bit: bool) -> Natural {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry = low_bit as u32;
+        for &limb in &self.limbs {
+            limbs.push(limb << 1 | carry);
+            carry = limb >> 31;
+        }
+        limbs.push(carry);
+        Natural::normalized(limbs)
+    }
+
+    /// ```
+    /// use aoc::day11::Natural;
+    /// let big = Natural::from(u64::MAX) * Natural::from(u64::MAX) + Natural::from(7u64);
+    /// assert_eq!(big.to_string(), "340282366920938463426481119284349108232");
+    /// assert_eq!(big.checked_sub(&big), Some(Natural::zero()));
+    /// assert_eq!(Natural::from(1u64).checked_sub(&big), None);
+    /// ```
+    pub fn checked_sub(&self, other: &Natural) -> Option<Natural> {
+        if *self < *other {
+            return None;
+        }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (idx, &limb) in self.limbs.iter().enumerate() {
+            let mut diff = limb as i64 - *other.limbs.get(idx).unwrap_or(&0) as i64 - borrow;
+            borrow = (diff < 0) as i64;
+            if diff < 0 {
+                diff += 1 << 32;
+            }
+            limbs.push(diff as u32);
+        }
+        Some(Natural::normalized(limbs))
+    }
+
+    /// Quotient and remainder, `None` when dividing by zero.
+    /// ```
+    /// use aoc::day11::Natural;
+    /// let big = Natural::from(u64::MAX) * Natural::from(1000u64) + Natural::from(17u64);
+    /// assert_eq!(big.div_rem(&Natural::from(1000u64)), Some((Natural::from(u64::MAX), Natural::from(17u64))));
+    /// assert_eq!(big.div_rem(&Natural::zero()), None);
+    /// ```
+    pub fn div_rem(&self, divisor: &Natural) -> Option<(Natural, Natural)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let mut quotient = vec![0u32; self.limbs.len()];
+        let mut remainder = Natural::zero();
+        for idx in (0..self.bit_len()).rev() {
+            remainder = remainder.shl1_with(self.bit(idx));
+            if remainder >= *divisor {
+                remainder = remainder.checked_sub(divisor).unwrap();
+                quotient[idx / 32] |= 1 << (idx % 32);
+            }
+        }
+        Some((Natural::normalized(quotient), remainder))
+    }
+
+    pub fn rem_u64(&self, modulus: u64) -> u64 {
+        self.limbs.iter().rev().fold(0u128, |acc, &limb| {
+            ((acc << 32) | limb as u128) % modulus as u128
+        }) as u64
+    }
+}
+
+impl From<u64> for Natural {
+    fn from(value: u64) -> Natural {
+        Natural::normalized(vec![value as u32, (value >> 32) as u32])
+    }
+}
+
+impl PartialOrd for Natural {
+    fn partial_cmp(&self, other: &Natural) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Natural {
+    fn cmp(&self, other: &Natural) -> std::cmp::Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl std::ops::Add for Natural {
+    type Output = Natural;
+
+    fn add(self, other: Natural) -> Natural {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for idx in 0..len {
+            let sum = *self.limbs.get(idx).unwrap_or(&0) as u64
+                + *other.limbs.get(idx).unwrap_or(&0) as u64
+                + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        Natural::normalized(limbs)
+    }
+}
+
+impl std::ops::Mul for Natural {
+    type Output = Natural;
+
+    fn mul(self, other: Natural) -> Natural {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Natural::normalized(limbs)
+    }
+}
+
+impl fmt::Display for Natural {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chunk = Natural::from(1_000_000_000u64);
+        let mut digits = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, remainder) = rest.div_rem(&chunk).unwrap();
+            digits.push(remainder.rem_u64(1_000_000_000));
+            rest = quotient;
+        }
+        match digits.split_last() {
+            None => write!(f, "0"),
+            Some((top, lower)) => {
+                write!(f, "{}", top)?;
+                for group in lower.iter().rev() {
+                    write!(f, "{:09}", group)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Exact arithmetic on `Natural`s; never overflows.
+pub struct ArbitraryPrecision;
+
+impl Arithmetic for ArbitraryPrecision {
+    type Value = Natural;
+
+    fn constant(&self, value: u64) -> Natural {
+        Natural::from(value)
+    }
+
+    fn add(&self, lhs: &Natural, rhs: &Natural) -> Result<Natural, EvalError> {
+        Ok(lhs.clone() + rhs.clone())
+    }
+
+    fn sub(&self, lhs: &Natural, rhs: &Natural) -> Result<Natural, EvalError> {
+        lhs.checked_sub(rhs).ok_or(EvalError::Underflow)
+    }
+
+    fn mul(&self, lhs: &Natural, rhs: &Natural) -> Result<Natural, EvalError> {
+        Ok(lhs.clone() * rhs.clone())
+    }
+
+    fn div(&self, lhs: &Natural, rhs: &Natural) -> Result<Natural, EvalError> {
+        lhs.div_rem(rhs)
+            .map(|(quotient, _)| quotient)
+            .ok_or(EvalError::DivisionByZero)
+    }
+
+    fn is_divisible_by(&self, value: &Natural, divisor: u64) -> bool {
+        value.rem_u64(divisor) == 0
+    }
+}
+
+/// Stores each worry level as its residues modulo the monkeys' divisors, which
+/// is all the divisibility tests need. Values stay small however long the
+/// simulation runs, but division and subtraction are unsupported: residues
+/// cannot tell whether a difference would go below zero, so they could not
+/// report `EvalError::Underflow` like the other backends.
+/// ```
+/// use aoc::day11::{EvalError, Op, Residues};
+/// let op: Op = "old - 3".parse().unwrap();
+/// assert_eq!(op.eval(2), Err(EvalError::Underflow));
+/// assert_eq!(
+///     op.eval_with(&Residues::new(vec![5, 7]), &vec![2, 2]),
+///     Err(EvalError::Unsupported("subtraction of residues"))
+/// );
+/// ```
+pub struct Residues {
+    moduli: Vec<u64>,
+}
+
+impl Residues {
+    pub fn new(moduli: Vec<u64>) -> Residues {
+        assert!(moduli.iter().all(|&m| m > 0), "Moduli must be positive");
+        Residues { moduli }
+    }
+
+    /// Residues modulo every monkey's `test_divisble_by`.
+    pub fn for_monkeys(monkeys: &[Monkey]) -> Residues {
+        Residues::new(monkeys.iter().map(|x| x.test_divisble_by).collect())
+    }
+
+    fn combine(&self, lhs: &[u64], rhs: &[u64], f: impl Fn(u128, u128, u128) -> u128) -> Vec<u64> {
+        self.moduli
+            .iter()
+            .zip(lhs.iter().zip(rhs))
+            .map(|(&m, (&a, &b))| (f(a as u128, b as u128, m as u128) % m as u128) as u64)
+            .collect()
+    }
+}
+
+impl Arithmetic for Residues {
+    type Value = Vec<u64>;
+
+    fn constant(&self, value: u64) -> Vec<u64> {
+        self.moduli.iter().map(|m| value % m).collect()
+    }
+
+    fn add(&self, lhs: &Vec<u64>, rhs: &Vec<u64>) -> Result<Vec<u64>, EvalError> {
+        Ok(self.combine(lhs, rhs, |a, b, _| a + b))
+    }
+
+    fn sub(&self, _: &Vec<u64>, _: &Vec<u64>) -> Result<Vec<u64>, EvalError> {
+        Err(EvalError::Unsupported("subtraction of residues"))
+    }
+
+    fn mul(&self, lhs: &Vec<u64>, rhs: &Vec<u64>) -> Result<Vec<u64>, EvalError> {
+        Ok(self.combine(lhs, rhs, |a, b, _| a * b))
+    }
+
+    fn div(&self, _: &Vec<u64>, _: &Vec<u64>) -> Result<Vec<u64>, EvalError> {
+        Err(EvalError::Unsupported("division of residues"))
+    }
+
+    fn is_divisible_by(&self, value: &Vec<u64>, divisor: u64) -> bool {
+        let idx = self
+            .moduli
+            .iter()
+            .position(|&m| m == divisor)
+            .unwrap_or_else(|| panic!("No residue kept modulo {}", divisor));
+        value[idx] == 0
+    }
 }
 
 impl Op {
-    pub fn eval(&self, arg: u64) -> u64 {
+    /// ```
+    /// use aoc::day11::{EvalError, Op};
+    /// let op: Op = "old * old".parse().unwrap();
+    /// assert_eq!(op.eval(9), Ok(81));
+    /// assert_eq!(op.eval(u64::MAX), Err(EvalError::Overflow));
+    /// assert_eq!("old - 3".parse::<Op>().unwrap().eval(2), Err(EvalError::Underflow));
+    /// assert_eq!("old / (old - old)".parse::<Op>().unwrap().eval(2), Err(EvalError::DivisionByZero));
+    /// ```
+    pub fn eval(&self, old: u64) -> Result<u64, EvalError> {
+        self.eval_with(&CheckedU64, &old)
+    }
+
+    /// ```
+    /// use aoc::day11::{ArbitraryPrecision, CheckedU128, Natural, Op, Residues};
+    /// let op: Op = "old * old + 1".parse().unwrap();
+    /// assert_eq!(op.eval_with(&CheckedU128, &(u64::MAX as u128)), Ok(u64::MAX as u128 * u64::MAX as u128 + 1));
+    /// assert_eq!(
+    ///     op.eval_with(&ArbitraryPrecision, &Natural::from(u64::MAX)).unwrap().to_string(),
+    ///     "340282366920938463426481119284349108226"
+    /// );
+    /// assert_eq!(op.eval_with(&Residues::new(vec![5, 7]), &vec![3, 3]), Ok(vec![0, 3]));
+    /// ```
+    pub fn eval_with<A: Arithmetic>(
+        &self,
+        arithmetic: &A,
+        old: &A::Value,
+    ) -> Result<A::Value, EvalError> {
         match self {
-            Op::Add(x) => arg + x,
-            Op::Mul(x) => arg * x,
-            Op::Square => arg * arg,
+            Op::Old => Ok(old.clone()),
+            Op::Const(value) => Ok(arithmetic.constant(*value)),
+            Op::Add(lhs, rhs) => arithmetic.add(
+                &lhs.eval_with(arithmetic, old)?,
+                &rhs.eval_with(arithmetic, old)?,
+            ),
+            Op::Sub(lhs, rhs) => arithmetic.sub(
+                &lhs.eval_with(arithmetic, old)?,
+                &rhs.eval_with(arithmetic, old)?,
+            ),
+            Op::Mul(lhs, rhs) => arithmetic.mul(
+                &lhs.eval_with(arithmetic, old)?,
+                &rhs.eval_with(arithmetic, old)?,
+            ),
+            Op::Div(lhs, rhs) => arithmetic.div(
+                &lhs.eval_with(arithmetic, old)?,
+                &rhs.eval_with(arithmetic, old)?,
+            ),
         }
     }
 }
@@ -26,7 +573,7 @@ pub fn reduce_worry_level_2(modulo: u64, level: u64) -> u64 {
 }
 
 #[derive(Clone, Debug)]
-struct Monkey {
+pub struct Monkey {
     pub items: Vec<u64>,
     pub op: Op,
     pub test_divisble_by: u64,
@@ -35,58 +582,42 @@ struct Monkey {
     pub inspection_count: usize,
 }
 
-fn simulate(
-    mut monkeys: Vec<Monkey>,
-    reduce_worry_level: Box<dyn Fn(u64) -> u64>,
-    iter_count: u32,
-) -> usize {
-    for _ in 0..iter_count {
-        for i in 0..monkeys.len() {
-            monkeys[i].inspection_count += monkeys[i].items.len();
-
-            let mut what_to_which = vec![];
-            for item in &monkeys[i].items {
-                let new_worry_level = reduce_worry_level(monkeys[i].op.eval(*item));
-                if new_worry_level % monkeys[i].test_divisble_by == 0 {
-                    what_to_which.push((new_worry_level, monkeys[i].throw_to_if_true));
-                } else {
-                    what_to_which.push((new_worry_level, monkeys[i].throw_to_if_false));
-                }
-            }
+impl Monkey {
+    /// Worry level of an item once this monkey has inspected it.
+    pub fn inspect<A: Arithmetic>(
+        &self,
+        arithmetic: &A,
+        item: &A::Value,
+    ) -> Result<A::Value, EvalError> {
+        self.op.eval_with(arithmetic, item)
+    }
 
-            monkeys[i].items = vec![];
-            for (what, to_which) in what_to_which {
-                monkeys[to_which].items.push(what);
-            }
+    /// Monkey that gets an item with the given worry level.
+    pub fn target<A: Arithmetic>(&self, arithmetic: &A, level: &A::Value) -> usize {
+        if arithmetic.is_divisible_by(level, self.test_divisble_by) {
+            self.throw_to_if_true
+        } else {
+            self.throw_to_if_false
         }
     }
-
-    let mut business = monkeys
-        .iter()
-        .map(|x| x.inspection_count)
-        .collect::<Vec<usize>>();
-    business.sort();
-    business.reverse();
-    business[0] * business[1]
 }
 
-pub fn run() {
-    let lines = read_lines("in/day11.in")
-        .unwrap()
-        .map(|x| x.unwrap())
-        .collect::<Vec<String>>();
+/// What happens to the worry level after a monkey inspects an item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relief {
+    None,
+    DivideBy(u64),
+}
 
-    let mut modulo = 1;
-    let monkeys: Vec<Monkey> = lines.split(|l| l.is_empty()).into_iter().map(|monkey_lines| {
+/// Parses the puzzle's monkey descriptions, separated by blank lines.
+pub fn parse_monkeys(lines: &[String]) -> Vec<Monkey> {
+    lines.split(|l| l.is_empty()).map(|monkey_lines| {
         let mut monkey_line = monkey_lines.iter();
         monkey_line.next();
         let items =
         scan!(monkey_line.next().unwrap(); ("  Starting items: ", [let items: u64],+: Vec<u64>) => items ).unwrap();
-        let op =
-        scan!(monkey_line.next().unwrap();
-            ("  Operation: new = old + ", let delta: u64) => Op::Add(delta),
-            ("  Operation: new = old * old") => Op::Square,
-            ("  Operation: new = old * ", let delta: u64) => Op::Mul(delta) 
+        let op = scan!(monkey_line.next().unwrap();
+            ("  Operation: new = ", ..expression) => expression.parse::<Op>().unwrap()
         ).unwrap();
         let test_divisble_by = scan!(monkey_line.next().unwrap();
             ("  Test: divisible by ", let divider: u64) => divider
@@ -97,17 +628,195 @@ pub fn run() {
         let throw_to_if_false = scan!(monkey_line.next().unwrap();
             ("    If false: throw to monkey ", let monkey: usize) => monkey
         ).unwrap();
-        modulo *= test_divisble_by;
 
         Monkey { items, op, test_divisble_by, throw_to_if_true, throw_to_if_false , inspection_count: 0}
-    }).collect();
+    }).collect()
+}
+
+/// Everything that happened during a simulation.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Simulation {
+    /// Inspections by each monkey, cumulative, after every round.
+    pub inspections_per_round: Vec<Vec<usize>>,
+    /// Monkeys that held each item, in order, starting with its first holder.
+    /// Items are numbered in the order of the starting items.
+    pub trajectories: Vec<Vec<usize>>,
+    /// Number of throws from one monkey to another.
+    pub throws: BTreeMap<(usize, usize), usize>,
+}
+
+impl Simulation {
+    /// Inspections by each monkey after the last round.
+    pub fn inspection_counts(&self) -> Vec<usize> {
+        self.inspections_per_round
+            .last()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Product of the two largest inspection counts.
+    pub fn monkey_business(&self) -> usize {
+        let mut business = self.inspection_counts();
+        business.sort();
+        business.reverse();
+        business[0] * business[1]
+    }
+
+    /// Graphviz graph of the monkeys, with throw counts as edge weights.
+    pub fn to_dot(&self) -> String {
+        let mut dot = "digraph monkeys {\n".to_owned();
+        for (monkey, count) in self.inspection_counts().iter().enumerate() {
+            dot += &format!(
+                "    {} [label=\"Monkey {}\\n{} inspections\"];\n",
+                monkey, monkey, count
+            );
+        }
+        for ((from, to), count) in &self.throws {
+            dot += &format!(
+                "    {} -> {} [label=\"{}\", weight={}];\n",
+                from, to, count, count
+            );
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+/// ```
+/// use aoc::day11::{parse_monkeys, reduce_worry_level, simulate};
+/// let lines: Vec<String> = std::fs::read_to_string("in/day11small.in")
+///     .unwrap()
+///     .lines()
+///     .map(String::from)
+///     .collect();
+/// let simulation = simulate(parse_monkeys(&lines), Box::new(reduce_worry_level), 20).unwrap();
+/// assert_eq!(simulation.inspection_counts(), vec![101, 95, 7, 105]);
+/// assert_eq!(simulation.inspections_per_round[0], vec![2, 4, 3, 5]);
+/// assert_eq!(simulation.monkey_business(), 10605);
+/// assert_eq!(simulation.trajectories[0][..3], [0, 3, 1]);
+/// assert_eq!(
+///     simulation.throws.values().sum::<usize>(),
+///     simulation.inspection_counts().iter().sum::<usize>()
+/// );
+/// assert!(simulation.to_dot().contains("    0 -> 3 [label="));
+/// ```
+pub fn simulate(
+    monkeys: Vec<Monkey>,
+    reduce_worry_level: Box<dyn Fn(u64) -> u64>,
+    iter_count: u32,
+) -> Result<Simulation, EvalError> {
+    simulate_impl(
+        &CheckedU64,
+        monkeys,
+        |level| Ok(reduce_worry_level(level)),
+        iter_count,
+    )
+}
+
+/// `simulate` with a chosen worry level representation.
+/// ```
+/// use aoc::day11::{parse_monkeys, simulate_with, ArbitraryPrecision, CheckedU64, EvalError, Relief, Residues};
+/// let lines: Vec<String> = std::fs::read_to_string("in/day11small.in")
+///     .unwrap()
+///     .lines()
+///     .map(String::from)
+///     .collect();
+/// let monkeys = parse_monkeys(&lines);
+///
+/// let residues = Residues::for_monkeys(&monkeys);
+/// let simulation = simulate_with(&residues, monkeys.clone(), Relief::None, 10_000).unwrap();
+/// assert_eq!(simulation.monkey_business(), 2713310158);
+///
+/// let exact = simulate_with(&ArbitraryPrecision, monkeys.clone(), Relief::None, 20).unwrap();
+/// assert_eq!(exact.inspection_counts(), vec![99, 97, 8, 103]);
+///
+/// assert_eq!(
+///     simulate_with(&CheckedU64, monkeys, Relief::None, 1_000).err(),
+///     Some(EvalError::Overflow)
+/// );
+/// ```
+pub fn simulate_with<A: Arithmetic>(
+    arithmetic: &A,
+    monkeys: Vec<Monkey>,
+    relief: Relief,
+    iter_count: u32,
+) -> Result<Simulation, EvalError> {
+    let divisor = match relief {
+        Relief::None => None,
+        Relief::DivideBy(divisor) => Some(arithmetic.constant(divisor)),
+    };
+    simulate_impl(
+        arithmetic,
+        monkeys,
+        |level| match &divisor {
+            None => Ok(level),
+            Some(divisor) => arithmetic.div(&level, divisor),
+        },
+        iter_count,
+    )
+}
+
+fn simulate_impl<A: Arithmetic, R: Fn(A::Value) -> Result<A::Value, EvalError>>(
+    arithmetic: &A,
+    mut monkeys: Vec<Monkey>,
+    relief: R,
+    iter_count: u32,
+) -> Result<Simulation, EvalError> {
+    let mut simulation = Simulation {
+        inspections_per_round: vec![],
+        trajectories: vec![],
+        throws: BTreeMap::new(),
+    };
+    // Worry levels and item numbers held by each monkey.
+    let mut items: Vec<Vec<(A::Value, usize)>> = vec![];
+    for (i, monkey) in monkeys.iter().enumerate() {
+        let mut held = vec![];
+        for item in &monkey.items {
+            held.push((arithmetic.constant(*item), simulation.trajectories.len()));
+            simulation.trajectories.push(vec![i]);
+        }
+        items.push(held);
+    }
+
+    for _ in 0..iter_count {
+        for i in 0..monkeys.len() {
+            monkeys[i].inspection_count += items[i].len();
+
+            for (item, id) in std::mem::take(&mut items[i]) {
+                let new_worry_level = relief(monkeys[i].inspect(arithmetic, &item)?)?;
+                let to_which = monkeys[i].target(arithmetic, &new_worry_level);
+                items[to_which].push((new_worry_level, id));
+                simulation.trajectories[id].push(to_which);
+                *simulation.throws.entry((i, to_which)).or_default() += 1;
+            }
+        }
+        simulation
+            .inspections_per_round
+            .push(monkeys.iter().map(|x| x.inspection_count).collect());
+    }
+
+    Ok(simulation)
+}
+
+pub fn run() {
+    let lines = read_lines("in/day11.in")
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect::<Vec<String>>();
+
+    let monkeys = parse_monkeys(&lines);
 
     println!(
         "Day 11, part 1 {}",
-        simulate(monkeys.clone(), Box::new(reduce_worry_level), 20)
+        simulate_with(&CheckedU64, monkeys.clone(), Relief::DivideBy(3), 20)
+            .unwrap()
+            .monkey_business()
     );
+    let residues = Residues::for_monkeys(&monkeys);
     println!(
         "Day 11, part 2 {}",
-        simulate(monkeys, Box::new(move |x| x % modulo), 10_000)
+        simulate_with(&residues, monkeys, Relief::None, 10_000)
+            .unwrap()
+            .monkey_business()
     );
 }
@@ -1,6 +1,7 @@
 use crate::utils::read_lines;
-use std::collections::HashSet;
-use std::collections::VecDeque;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::fmt;
 
 fn is_valid(loc: &(i32, i32), grid: &[Vec<char>]) -> Option<(usize, usize)> {
     if loc.0 >= 0 && (loc.0 as usize) < grid.len() && loc.1 >= 0 && (loc.1 as usize) < grid[0].len()
@@ -10,68 +11,472 @@ fn is_valid(loc: &(i32, i32), grid: &[Vec<char>]) -> Option<(usize, usize)> {
     None
 }
 
-fn bfs(
+/// Heights as `'a'..='z'`, with `S` and `E` replaced by their heights.
+pub struct Heightmap {
+    pub grid: Vec<Vec<char>>,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HeightmapError {
+    Empty,
+    MissingStart,
+    MissingEnd,
+}
+
+impl fmt::Display for HeightmapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeightmapError::Empty => write!(f, "empty heightmap"),
+            HeightmapError::MissingStart => write!(f, "no start marker 'S'"),
+            HeightmapError::MissingEnd => write!(f, "no end marker 'E'"),
+        }
+    }
+}
+
+/// Parses the puzzle input. Both the `S` and the `E` marker must be present.
+/// ```
+/// use aoc::day12::{parse_heightmap, HeightmapError};
+/// let map = parse_heightmap(&["Sbc", "zzE"]).unwrap();
+/// assert_eq!((map.start, map.end), ((0, 0), (1, 2)));
+/// assert_eq!(map.grid, vec![vec!['a', 'b', 'c'], vec!['z', 'z', 'z']]);
+/// assert_eq!(parse_heightmap(&["abE"]).err(), Some(HeightmapError::MissingStart));
+/// assert_eq!(parse_heightmap(&["Sbc"]).err(), Some(HeightmapError::MissingEnd));
+/// assert_eq!(parse_heightmap::<&str>(&[]).err(), Some(HeightmapError::Empty));
+/// ```
+pub fn parse_heightmap<S: AsRef<str>>(lines: &[S]) -> Result<Heightmap, HeightmapError> {
+    let mut grid: Vec<Vec<char>> = lines.iter().map(|l| l.as_ref().chars().collect()).collect();
+    if grid.first().is_none_or(|row| row.is_empty()) {
+        return Err(HeightmapError::Empty);
+    }
+    let mut start = None;
+    let mut end = None;
+    for (x, line) in grid.iter().enumerate() {
+        if let Some((y, _)) = line.iter().enumerate().find(|x| *x.1 == 'S') {
+            start = Some((x, y));
+        }
+        if let Some((y, _)) = line.iter().enumerate().find(|x| *x.1 == 'E') {
+            end = Some((x, y));
+        }
+    }
+    let start = start.ok_or(HeightmapError::MissingStart)?;
+    let end = end.ok_or(HeightmapError::MissingEnd)?;
+    grid[start.0][start.1] = 'a';
+    grid[end.0][end.1] = 'z';
+    Ok(Heightmap { grid, start, end })
+}
+
+/// Shortest route from `start` to the nearest cell accepted by `is_target`,
+/// both ends included. `check(next, cur)` decides whether a step between
+/// heights is allowed. `None` if no target is reachable.
+/// ```
+/// use aoc::day12::{bfs, parse_heightmap};
+/// let map = parse_heightmap(&["Sbc", "zzd", "Efe"]).unwrap();
+/// let end = map.end;
+/// let path = bfs(map.start, &|loc| loc == end, &map.grid, Box::new(|next, cur| next - cur <= 1));
+/// assert_eq!(path, None);
+///
+/// let map = parse_heightmap(&["Sabqponm", "abcryxxl", "accszExk", "acctuvwj", "abdefghi"]).unwrap();
+/// let end = map.end;
+/// let path = bfs(map.start, &|loc| loc == end, &map.grid, Box::new(|next, cur| next - cur <= 1));
+/// assert_eq!(path.unwrap().len() - 1, 31);
+/// ```
+pub fn bfs(
     start: (usize, usize),
-    dest_height: char,
+    is_target: &dyn Fn((usize, usize)) -> bool,
     grid: &[Vec<char>],
     check: Box<dyn Fn(i32, i32) -> bool>,
-) -> i32 {
+) -> Option<Vec<(usize, usize)>> {
     let mut queue = VecDeque::new();
-    let mut visited: HashSet<(usize, usize)> = HashSet::new();
-    queue.push_back((start, 0));
-    while let Some((loc, distance)) = queue.pop_front() {
-        if visited.contains(&loc) {
-            continue;
+    // Cell each visited cell was first reached from; the start points to itself.
+    let mut came_from: Vec<Vec<Option<(usize, usize)>>> =
+        vec![vec![None; grid[0].len()]; grid.len()];
+    came_from[start.0][start.1] = Some(start);
+    queue.push_back(start);
+    while let Some(loc) = queue.pop_front() {
+        if is_target(loc) {
+            let mut path = vec![loc];
+            let mut cur = loc;
+            while cur != start {
+                cur = came_from[cur.0][cur.1].unwrap();
+                path.push(cur);
+            }
+            path.reverse();
+            return Some(path);
         }
-        visited.insert(loc);
 
         let current_height = grid[loc.0][loc.1];
-        if current_height as char == dest_height {
-            return distance;
+        for (dx, dy) in [(0, 1), (1, 0), (-1, 0), (0, -1)] {
+            let maybe_next_loc = (loc.0 as i32 + dx, loc.1 as i32 + dy);
+            if let Some(next_loc) = is_valid(&maybe_next_loc, grid) {
+                let next_height = grid[next_loc.0][next_loc.1] as i32;
+                if came_from[next_loc.0][next_loc.1].is_none()
+                    && check(next_height, current_height as i32)
+                {
+                    came_from[next_loc.0][next_loc.1] = Some(loc);
+                    queue.push_back(next_loc);
+                }
+            }
         }
+    }
+    None
+}
+
+/// Result of a multi-source search: fewest steps to every cell and the
+/// neighbour each cell was first reached from, so routes can be recovered.
+pub struct ShortestRoutes {
+    pub distance: Vec<Vec<Option<u32>>>,
+    /// `None` for sources and unreached cells.
+    pub came_from: Vec<Vec<Option<(usize, usize)>>>,
+}
 
+impl ShortestRoutes {
+    /// Route from `from` back to the source that reaches it, both ends
+    /// included. `None` if `from` was not reached.
+    pub fn route(&self, from: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        self.distance[from.0][from.1]?;
+        let mut path = vec![from];
+        let mut cur = from;
+        while let Some(prev) = self.came_from[cur.0][cur.1] {
+            path.push(prev);
+            cur = prev;
+        }
+        Some(path)
+    }
+}
+
+/// Searches from all `sources` at once. `check(next, cur)` decides whether
+/// a step between heights is allowed.
+/// ```
+/// use aoc::day12::{best_starts, parse_heightmap, shortest_routes};
+/// let map = parse_heightmap(&["Sabqponm", "abcryxxl", "accszExk", "acctuvwj", "abdefghi"]).unwrap();
+/// let routes = shortest_routes(&[map.end], &map.grid, Box::new(|next, cur| cur - next <= 1));
+/// let (best, starts) = best_starts(&routes.distance, &map.grid, 'a').unwrap();
+/// let route = routes.route(starts[0]).unwrap();
+/// assert_eq!((route.len() - 1) as u32, best);
+/// assert_eq!((route[0], route[route.len() - 1]), ((4, 0), map.end));
+/// assert_eq!(routes.route(map.end), Some(vec![map.end]));
+/// ```
+pub fn shortest_routes(
+    sources: &[(usize, usize)],
+    grid: &[Vec<char>],
+    check: Box<dyn Fn(i32, i32) -> bool>,
+) -> ShortestRoutes {
+    let mut distance: Vec<Vec<Option<u32>>> = vec![vec![None; grid[0].len()]; grid.len()];
+    let mut came_from: Vec<Vec<Option<(usize, usize)>>> =
+        vec![vec![None; grid[0].len()]; grid.len()];
+    let mut queue = VecDeque::new();
+    for &source in sources {
+        if distance[source.0][source.1].is_none() {
+            distance[source.0][source.1] = Some(0);
+            queue.push_back(source);
+        }
+    }
+    while let Some(loc) = queue.pop_front() {
+        let current_distance = distance[loc.0][loc.1].unwrap();
+        let current_height = grid[loc.0][loc.1];
         for (dx, dy) in [(0, 1), (1, 0), (-1, 0), (0, -1)] {
             let maybe_next_loc = (loc.0 as i32 + dx, loc.1 as i32 + dy);
             if let Some(next_loc) = is_valid(&maybe_next_loc, grid) {
                 let next_height = grid[next_loc.0][next_loc.1] as i32;
-                if check(next_height, current_height as i32) {
-                    queue.push_back((next_loc, distance + 1));
+                if distance[next_loc.0][next_loc.1].is_none()
+                    && check(next_height, current_height as i32)
+                {
+                    distance[next_loc.0][next_loc.1] = Some(current_distance + 1);
+                    came_from[next_loc.0][next_loc.1] = Some(loc);
+                    queue.push_back(next_loc);
                 }
             }
         }
     }
-    panic!("Could not find path");
+    ShortestRoutes {
+        distance,
+        came_from,
+    }
 }
 
-pub fn run() {
-    let lines = read_lines("in/day12.in");
+/// Fewest steps to every cell from the nearest of `sources`, `None` where no
+/// source can reach. Use `shortest_routes` to also recover the routes.
+/// ```
+/// use aoc::day12::{distance_field, parse_heightmap};
+/// let map = parse_heightmap(&["Sbc", "zzd", "Efe"]).unwrap();
+/// let field = distance_field(&[map.start], &map.grid, Box::new(|next, cur| next - cur <= 1));
+/// assert_eq!(field[2], vec![None, Some(5), Some(4)]);
+/// assert_eq!(field[1][0], None);
+/// ```
+pub fn distance_field(
+    sources: &[(usize, usize)],
+    grid: &[Vec<char>],
+    check: Box<dyn Fn(i32, i32) -> bool>,
+) -> Vec<Vec<Option<u32>>> {
+    shortest_routes(sources, grid, check).distance
+}
+
+/// Closest of `targets` in a distance field, with its distance.
+pub fn nearest(
+    field: &[Vec<Option<u32>>],
+    targets: &[(usize, usize)],
+) -> Option<((usize, usize), u32)> {
+    targets
+        .iter()
+        .filter_map(|&loc| Some((loc, field[loc.0][loc.1]?)))
+        .min_by_key(|&(_, distance)| distance)
+}
+
+/// Reachable cells of the given height that share the smallest distance.
+/// ```
+/// use aoc::day12::{best_starts, distance_field, parse_heightmap};
+/// let map = parse_heightmap(&["Sabqponm", "abcryxxl", "accszExk", "acctuvwj", "abdefghi"]).unwrap();
+/// let field = distance_field(&[map.end], &map.grid, Box::new(|next, cur| cur - next <= 1));
+/// assert_eq!(best_starts(&field, &map.grid, 'a'), Some((29, vec![(4, 0)])));
+/// ```
+pub fn best_starts(
+    field: &[Vec<Option<u32>>],
+    grid: &[Vec<char>],
+    height: char,
+) -> Option<(u32, Vec<(usize, usize)>)> {
+    let candidates: Vec<((usize, usize), u32)> = grid
+        .iter()
+        .enumerate()
+        .flat_map(|(x, row)| row.iter().enumerate().map(move |(y, h)| ((x, y), *h)))
+        .filter(|&(_, h)| h == height)
+        .filter_map(|(loc, _)| Some((loc, field[loc.0][loc.1]?)))
+        .collect();
+    let best = candidates.iter().map(|&(_, distance)| distance).min()?;
+    Some((
+        best,
+        candidates
+            .into_iter()
+            .filter(|&(_, distance)| distance == best)
+            .map(|(loc, _)| loc)
+            .collect(),
+    ))
+}
 
-    let mut start = (0, 0);
-    let mut end = (0, 0);
-    let mut grid: Vec<Vec<char>> = vec![];
-    let mut distance: Vec<Vec<u32>> = vec![];
-    for line in lines.unwrap() {
-        grid.push(line.unwrap().chars().collect());
-        distance.push(vec![1_000_000_000; grid[0].len()]);
+/// For every height present in the grid, which cells of that height are
+/// reachable in the distance field.
+/// ```
+/// use aoc::day12::{distance_field, parse_heightmap, reachability_by_height};
+/// let map = parse_heightmap(&["Sbc", "zzd", "Efe"]).unwrap();
+/// let field = distance_field(&[map.start], &map.grid, Box::new(|next, cur| next - cur <= 1));
+/// let maps = reachability_by_height(&field, &map.grid);
+/// assert_eq!(maps[&'z'], vec![vec![false; 3]; 3]);
+/// assert_eq!(maps[&'e'], vec![vec![false; 3], vec![false; 3], vec![false, false, true]]);
+/// ```
+pub fn reachability_by_height(
+    field: &[Vec<Option<u32>>],
+    grid: &[Vec<char>],
+) -> BTreeMap<char, Vec<Vec<bool>>> {
+    let mut maps: BTreeMap<char, Vec<Vec<bool>>> = BTreeMap::new();
+    for (x, row) in grid.iter().enumerate() {
+        for (y, height) in row.iter().enumerate() {
+            let map = maps
+                .entry(*height)
+                .or_insert_with(|| vec![vec![false; row.len()]; grid.len()]);
+            map[x][y] = field[x][y].is_some();
+        }
     }
+    maps
+}
 
-    for (x, line) in grid.iter().enumerate() {
-        if let Some((y, _)) = line.iter().enumerate().find(|x| *x.1 == 'S') {
-            start = (x, y);
+/// Which steps are allowed and what they cost, for `least_effort`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClimbRules {
+    /// Largest height gain in one step; `None` for no limit.
+    pub max_up: Option<u32>,
+    /// Largest height loss in one step; `None` for no limit.
+    pub max_down: Option<u32>,
+    /// Whether diagonal steps are allowed as well.
+    pub diagonal: bool,
+    pub step_cost: u64,
+    /// Extra cost per unit of height gained.
+    pub climb_cost: u64,
+    /// Extra cost per unit of height lost.
+    pub descent_cost: u64,
+}
+
+impl Default for ClimbRules {
+    /// The puzzle's rules: climb at most one, descend freely, every step costs 1.
+    fn default() -> ClimbRules {
+        ClimbRules {
+            max_up: Some(1),
+            max_down: None,
+            diagonal: false,
+            step_cost: 1,
+            climb_cost: 0,
+            descent_cost: 0,
         }
-        if let Some((y, _)) = line.iter().enumerate().find(|x| *x.1 == 'E') {
-            end = (x, y);
+    }
+}
+
+impl ClimbRules {
+    fn steps(&self) -> &'static [(i32, i32)] {
+        if self.diagonal {
+            &[
+                (0, 1),
+                (1, 0),
+                (-1, 0),
+                (0, -1),
+                (1, 1),
+                (1, -1),
+                (-1, 1),
+                (-1, -1),
+            ]
+        } else {
+            &[(0, 1), (1, 0), (-1, 0), (0, -1)]
         }
     }
-    grid[start.0][start.1] = 'a';
-    grid[end.0][end.1] = 'z';
 
-    println!(
-        "Day 12, part 1 {}",
-        bfs(start, 'z', &grid, Box::new(|next, cur| next - cur <= 1))
-    );
-    println!(
-        "Day 12, part 2 {}",
-        bfs(end, 'a', &grid, Box::new(|next, cur| cur - next <= 1))
-    );
+    /// Cost of stepping from height `cur` to `next`, `None` if not allowed.
+    pub fn cost(&self, cur: char, next: char) -> Option<u64> {
+        let delta = next as i64 - cur as i64;
+        let (up, down) = (delta.max(0) as u64, (-delta).max(0) as u64);
+        if self.max_up.is_some_and(|max| up > max as u64)
+            || self.max_down.is_some_and(|max| down > max as u64)
+        {
+            return None;
+        }
+        Some(self.step_cost + up * self.climb_cost + down * self.descent_cost)
+    }
+}
+
+/// Cheapest route from `start` to any cell accepted by `is_target` under
+/// `rules`, found with Dijkstra's algorithm. Returns the total cost and the
+/// route, both ends included.
+/// ```
+/// use aoc::day12::{least_effort, parse_heightmap, ClimbRules};
+/// let map = parse_heightmap(&["Sabqponm", "abcryxxl", "accszExk", "acctuvwj", "abdefghi"]).unwrap();
+/// let end = map.end;
+/// let (cost, path) = least_effort(map.start, &|loc| loc == end, &map.grid, &ClimbRules::default()).unwrap();
+/// assert_eq!((cost, path.len()), (31, 32));
+///
+/// let diagonal = ClimbRules{diagonal: true, ..ClimbRules::default()};
+/// let (cost, _) = least_effort(map.start, &|loc| loc == end, &map.grid, &diagonal).unwrap();
+/// assert!(cost < 31);
+///
+/// let no_descent = ClimbRules{max_down: Some(0), ..ClimbRules::default()};
+/// assert!(least_effort(map.start, &|loc| loc == end, &map.grid, &no_descent).is_some());
+/// let flat = ClimbRules{max_up: Some(0), ..ClimbRules::default()};
+/// assert_eq!(least_effort(map.start, &|loc| loc == end, &map.grid, &flat), None);
+/// ```
+pub fn least_effort(
+    start: (usize, usize),
+    is_target: &dyn Fn((usize, usize)) -> bool,
+    grid: &[Vec<char>],
+    rules: &ClimbRules,
+) -> Option<(u64, Vec<(usize, usize)>)> {
+    let mut cost: Vec<Vec<Option<u64>>> = vec![vec![None; grid[0].len()]; grid.len()];
+    let mut came_from: Vec<Vec<Option<(usize, usize)>>> =
+        vec![vec![None; grid[0].len()]; grid.len()];
+    let mut queue = BinaryHeap::new();
+    cost[start.0][start.1] = Some(0);
+    queue.push(Reverse((0, start)));
+    while let Some(Reverse((current_cost, loc))) = queue.pop() {
+        if cost[loc.0][loc.1] != Some(current_cost) {
+            continue;
+        }
+        if is_target(loc) {
+            let mut path = vec![loc];
+            let mut cur = loc;
+            while let Some(prev) = came_from[cur.0][cur.1] {
+                path.push(prev);
+                cur = prev;
+            }
+            path.reverse();
+            return Some((current_cost, path));
+        }
+
+        for (dx, dy) in rules.steps() {
+            let maybe_next_loc = (loc.0 as i32 + dx, loc.1 as i32 + dy);
+            if let Some(next_loc) = is_valid(&maybe_next_loc, grid) {
+                let step = match rules.cost(grid[loc.0][loc.1], grid[next_loc.0][next_loc.1]) {
+                    Some(step) => step,
+                    None => continue,
+                };
+                let next_cost = current_cost + step;
+                if cost[next_loc.0][next_loc.1].is_none_or(|known| next_cost < known) {
+                    cost[next_loc.0][next_loc.1] = Some(next_cost);
+                    came_from[next_loc.0][next_loc.1] = Some(loc);
+                    queue.push(Reverse((next_cost, next_loc)));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Draws `path` over a grid of the given size like the puzzle statement:
+/// each step as an arrow towards the next cell, the last cell as `E` and
+/// every other cell as `.`. Diagonal steps, as taken by `least_effort` with
+/// `diagonal` rules, are drawn as `↗↘↙↖`.
+///
+/// Panics if two consecutive cells of `path` are not neighbours.
+/// ```
+/// use aoc::day12::{least_effort, parse_heightmap, render_path, ClimbRules};
+/// let path = [(0, 0), (1, 0), (1, 1), (0, 1), (0, 2)];
+/// assert_eq!(render_path((2, 3), &path), "v>E\n>^.\n");
+///
+/// let map = parse_heightmap(&["Sabqponm", "abcryxxl", "accszExk", "acctuvwj", "abdefghi"]).unwrap();
+/// let end = map.end;
+/// let diagonal = ClimbRules{diagonal: true, ..ClimbRules::default()};
+/// let (_, path) = least_effort(map.start, &|loc| loc == end, &map.grid, &diagonal).unwrap();
+/// assert_eq!(
+///     render_path((5, 8), &path),
+///     "↘..v<<<<\n.v.v↘<.^\n.v.v.E↖^\n.↘.>>>^^\n..>>>>>^\n"
+/// );
+/// ```
+pub fn render_path(dimensions: (usize, usize), path: &[(usize, usize)]) -> String {
+    let mut canvas = vec![vec!['.'; dimensions.1]; dimensions.0];
+    for step in path.windows(2) {
+        let (from, to) = (step[0], step[1]);
+        canvas[from.0][from.1] = match (to.0 as i32 - from.0 as i32, to.1 as i32 - from.1 as i32) {
+            (1, 0) => 'v',
+            (-1, 0) => '^',
+            (0, 1) => '>',
+            (0, -1) => '<',
+            (1, 1) => '↘',
+            (1, -1) => '↙',
+            (-1, 1) => '↗',
+            (-1, -1) => '↖',
+            _ => panic!(
+                "Path is not made of neighbouring cells: {:?} -> {:?}",
+                from, to
+            ),
+        };
+    }
+    if let Some(last) = path.last() {
+        canvas[last.0][last.1] = 'E';
+    }
+    canvas
+        .into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+
+pub fn run() {
+    let lines: Vec<String> = read_lines("in/day12.in")
+        .unwrap()
+        .map(|line| line.unwrap())
+        .collect();
+    let map = parse_heightmap(&lines).unwrap();
+    let grid = &map.grid;
+    let end = map.end;
+
+    let path = bfs(
+        map.start,
+        &|loc| loc == end,
+        grid,
+        Box::new(|next, cur| next - cur <= 1),
+    )
+    .unwrap();
+    let dimensions = (grid.len(), grid[0].len());
+    print!("{}", render_path(dimensions, &path));
+    println!("Day 12, part 1 {}", path.len() - 1);
+
+    let routes = shortest_routes(&[end], grid, Box::new(|next, cur| cur - next <= 1));
+    let (best, starts) = best_starts(&routes.distance, grid, 'a').unwrap();
+    let path = routes.route(starts[0]).unwrap();
+    print!("{}", render_path(dimensions, &path));
+    println!("Day 12, part 2 {}", best);
 }
@@ -1,12 +1,87 @@
 use crate::utils::read_lines;
 use std::cmp::Ordering;
-use std::{iter::Peekable, str::Chars};
+use std::fmt;
+use std::str::FromStr;
 
+/// A packet: an integer or a list of packets.
 #[derive(Clone, Debug, PartialEq, Eq)]
-enum Value {
+pub enum Value {
     Number(i32),
     List(Vec<Value>),
 }
+
+/// Builds a list packet from bracket syntax. Negative numbers and other
+/// expressions need parentheses.
+/// ```
+/// use aoc::day13::Value;
+/// assert_eq!(aoc::packet![], Value::List(vec![]));
+/// assert_eq!(
+///     aoc::packet![1, [], [(-2)]],
+///     Value::List(vec![Value::Number(1), Value::List(vec![]), Value::List(vec![Value::Number(-2)])])
+/// );
+/// ```
+#[macro_export]
+macro_rules! packet {
+    (@elem [$($inner:tt)*]) => { $crate::packet![$($inner)*] };
+    (@elem $number:expr) => { $crate::day13::Value::Number($number) };
+    ($($elem:tt),* $(,)?) => {
+        $crate::day13::Value::List(vec![$($crate::packet!(@elem $elem)),*])
+    };
+}
+
+impl Value {
+    /// Builds a list packet from anything convertible into packets.
+    /// ```
+    /// use aoc::day13::Value;
+    /// assert_eq!(Value::list([1, 2]), aoc::packet![1, 2]);
+    /// assert_eq!(Value::list([Value::list([3])]), aoc::packet![[3]]);
+    /// ```
+    pub fn list<T: Into<Value>>(items: impl IntoIterator<Item = T>) -> Value {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+
+    /// Wraps the packet in a single-element list, as the comparison does for
+    /// mixed pairs.
+    pub fn wrap(self) -> Value {
+        Value::List(vec![self])
+    }
+}
+
+impl From<i32> for Value {
+    fn from(number: i32) -> Value {
+        Value::Number(number)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Value {
+        Value::List(items)
+    }
+}
+
+impl fmt::Display for Value {
+    /// Writes the packet back in bracket syntax.
+    /// ```
+    /// use aoc::day13::Value;
+    /// let text = "[[1],[2,3,4],[],10]";
+    /// assert_eq!(text.parse::<Value>().unwrap().to_string(), text);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
 impl PartialOrd for Value {
     fn partial_cmp(&self, b: &Value) -> Option<Ordering> {
         Some(self.cmp(b))
@@ -36,79 +111,627 @@ impl Ord for Value {
                 }
                 a_list.len().cmp(&b_list.len())
             }
-            (a @ Value::Number(_), b) => Value::List(vec![a.clone()]).cmp(b),
-            (a, b @ Value::Number(_)) => a.cmp(&Value::List(vec![b.clone()])),
+            (a @ Value::Number(_), b) => a.clone().wrap().cmp(b),
+            (a, b @ Value::Number(_)) => a.cmp(&b.clone().wrap()),
+        }
+    }
+}
+
+/// Error from parsing a packet, with the byte offset where it went wrong.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {}: {}", self.position, self.reason)
+    }
+}
+
+struct PacketParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl PacketParser<'_> {
+    fn error<T>(&self, reason: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position,
+            reason: reason.to_owned(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some('[') => {
+                self.position += 1;
+                self.list()
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => self.error("expected '[' or a number"),
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn list(&mut self) -> Result<Value, ParseError> {
+        let mut vals = vec![];
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Value::List(vals));
+        }
+        loop {
+            vals.push(self.value()?);
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(Value::List(vals));
+                }
+                Some(_) => return self.error("expected ',' or ']'"),
+                None => return self.error("unexpected end of input"),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        let digits = self.input[self.position..]
+            .bytes()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return self.error("expected a digit");
+        }
+        self.position += digits;
+        match self.input[start..self.position].parse() {
+            Ok(number) => Ok(Value::Number(number)),
+            Err(_) => Err(ParseError {
+                position: start,
+                reason: "number out of range".to_owned(),
+            }),
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = ParseError;
+
+    /// Parses the bracket syntax, e.g. `[1,[2,3],[]]`.
+    /// ```
+    /// use aoc::day13::{ParseError, Value};
+    /// let packet: Value = "[1,[2,-3],[]]".parse().unwrap();
+    /// assert_eq!(packet, aoc::packet![1, [2, (-3)], []]);
+    /// assert_eq!("7".parse(), Ok(Value::Number(7)));
+    /// assert_eq!(
+    ///     "[1,,2]".parse::<Value>(),
+    ///     Err(ParseError{position: 3, reason: "expected '[' or a number".to_owned()})
+    /// );
+    /// assert_eq!("[1 2]".parse::<Value>().unwrap_err().position, 2);
+    /// assert_eq!("[[1]".parse::<Value>().unwrap_err().reason, "unexpected end of input");
+    /// assert_eq!("[1]]".parse::<Value>().unwrap_err().position, 3);
+    /// assert_eq!("[99999999999]".parse::<Value>().unwrap_err().reason, "number out of range");
+    /// ```
+    fn from_str(s: &str) -> Result<Value, ParseError> {
+        let mut parser = PacketParser {
+            input: s,
+            position: 0,
+        };
+        let value = parser.value()?;
+        if parser.position != s.len() {
+            return parser.error("unexpected trailing input");
+        }
+        Ok(value)
+    }
+}
+
+/// A general JSON value. Numbers are kept as `f64`, object members in
+/// source order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "boolean",
+            Json::Number(_) => "number",
+            Json::String(_) => "string",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        }
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    /// Writes compact JSON.
+    /// ```
+    /// use aoc::day13::Json;
+    /// let json: Json = r#" { "a" : [1, -2.5, null, true], "b\n": "\"x\"" } "#.parse().unwrap();
+    /// assert_eq!(json.to_string(), r#"{"a":[1,-2.5,null,true],"b\n":"\"x\""}"#);
+    /// assert_eq!(Json::Array(vec![Json::Number(f64::INFINITY)]).to_string(), "[null]");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            // JSON has no infinities or NaN; write them as `null` like
+            // JavaScript does.
+            Json::Number(n) if !n.is_finite() => write!(f, "null"),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_json_string(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn error<T>(&self, reason: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position,
+            reason: reason.to_owned(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+        self.input[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.peek() != Some(c) {
+            return self.error(&format!("expected '{}'", c));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, ParseError> {
+        if !self.input[self.position..].starts_with(word) {
+            return self.error("expected a value");
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, ParseError> {
+        match self.peek() {
+            Some('[') => {
+                self.position += 1;
+                Ok(Json::Array(self.sequence(']', |parser| parser.value())?))
+            }
+            Some('{') => {
+                self.position += 1;
+                Ok(Json::Object(self.sequence('}', |parser| {
+                    if parser.peek() != Some('"') {
+                        return parser.error("expected a string key");
+                    }
+                    let key = parser.string()?;
+                    parser.expect(':')?;
+                    Ok((key, parser.value()?))
+                })?))
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => self.error("expected a value"),
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    /// Comma separated items up to `close`, the opening bracket already consumed.
+    fn sequence<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![];
+        if self.peek() == Some(close) {
+            self.position += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(c) if c == close => {
+                    self.position += 1;
+                    return Ok(items);
+                }
+                Some(_) => return self.error(&format!("expected ',' or '{}'", close)),
+                None => return self.error("unexpected end of input"),
+            }
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let count = self.input[self.position..]
+            .bytes()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        self.position += count;
+        count
+    }
+
+    fn number(&mut self) -> Result<Json, ParseError> {
+        let start = self.position;
+        if self.input[self.position..].starts_with('-') {
+            self.position += 1;
+        }
+        let int_start = self.position;
+        match self.digits() {
+            0 => return self.error("expected a digit"),
+            n if n > 1 && self.input[int_start..].starts_with('0') => {
+                self.position = int_start;
+                return self.error("leading zeros are not allowed");
+            }
+            _ => {}
+        }
+        if self.input[self.position..].starts_with('.') {
+            self.position += 1;
+            if self.digits() == 0 {
+                return self.error("expected a digit");
+            }
+        }
+        if self.input[self.position..].starts_with(['e', 'E']) {
+            self.position += 1;
+            if self.input[self.position..].starts_with(['+', '-']) {
+                self.position += 1;
+            }
+            if self.digits() == 0 {
+                return self.error("expected a digit");
+            }
+        }
+        let number: f64 = self.input[start..self.position].parse().unwrap();
+        if !number.is_finite() {
+            return Err(ParseError {
+                position: start,
+                reason: "number out of range".to_owned(),
+            });
+        }
+        Ok(Json::Number(number))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.position += 1;
+        let mut result = String::new();
+        loop {
+            let c = match self.input[self.position..].chars().next() {
+                Some(c) => c,
+                None => return self.error("unterminated string"),
+            };
+            match c {
+                '"' => {
+                    self.position += 1;
+                    return Ok(result);
+                }
+                '\\' => {
+                    self.position += 1;
+                    result.push(self.escape()?);
+                }
+                c if (c as u32) < 0x20 => return self.error("control character in string"),
+                c => {
+                    self.position += c.len_utf8();
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.input[self.position..].chars().next() {
+            Some(c) => c,
+            None => return self.error("unterminated string"),
+        };
+        self.position += c.len_utf8();
+        Ok(match c {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.hex4()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    if !self.input[self.position..].starts_with("\\u") {
+                        return self.error("expected a low surrogate");
+                    }
+                    self.position += 2;
+                    let low = self.hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return self.error("expected a low surrogate");
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                match char::from_u32(code) {
+                    Some(c) => c,
+                    None => return self.error("invalid unicode escape"),
+                }
+            }
+            _ => {
+                self.position -= c.len_utf8();
+                return self.error("invalid escape");
+            }
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        match self.input.get(self.position..self.position + 4) {
+            Some(hex) if hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                self.position += 4;
+                Ok(u32::from_str_radix(hex, 16).unwrap())
+            }
+            _ => self.error("expected four hex digits"),
         }
     }
 }
 
-fn parse_number(it: &mut Peekable<Chars>) -> Value {
-    let mut number_input = String::new();
-    while let Some(next_char) = it.peek() {
-        if next_char.is_ascii_digit() {
-            number_input.push(it.next().unwrap());
-        } else {
-            assert!(
-                next_char == &',' || next_char == &']',
-                "Expected comma or end of list"
-            );
-            break;
+impl FromStr for Json {
+    type Err = ParseError;
+
+    /// Parses a JSON document, surrounding whitespace allowed.
+    /// ```
+    /// use aoc::day13::{Json, ParseError};
+    /// assert_eq!(" [ -1 , 2e1 ] ".parse(), Ok(Json::Array(vec![Json::Number(-1.0), Json::Number(20.0)])));
+    /// assert_eq!(r#""\u00e9\ud83d\ude00""#.parse(), Ok(Json::String("é😀".to_owned())));
+    /// assert_eq!("[1,]".parse::<Json>().unwrap_err().position, 3);
+    /// assert_eq!("01".parse::<Json>().unwrap_err().reason, "leading zeros are not allowed");
+    /// assert_eq!(
+    ///     "[1e400]".parse::<Json>(),
+    ///     Err(ParseError{position: 1, reason: "number out of range".to_owned()})
+    /// );
+    /// assert_eq!("{\"a\" 1}".parse::<Json>().unwrap_err().reason, "expected ':'");
+    /// assert_eq!("[] []".parse::<Json>().unwrap_err().reason, "unexpected trailing input");
+    /// ```
+    fn from_str(s: &str) -> Result<Json, ParseError> {
+        let mut parser = JsonParser {
+            input: s,
+            position: 0,
+        };
+        let json = parser.value()?;
+        if parser.peek().is_some() {
+            return parser.error("unexpected trailing input");
         }
+        Ok(json)
     }
-    Value::Number(number_input.parse().unwrap())
 }
 
-fn parse_list(it: &mut Peekable<Chars>) -> Value {
-    let mut vals = vec![];
-    while let Some(next_char) = it.peek() {
-        if next_char == &']' {
-            it.next().unwrap();
-            break;
+/// Why a JSON document is not a packet. `path` holds the array indices
+/// leading to the offending value.
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonError {
+    Syntax(ParseError),
+    NotAnInteger {
+        path: Vec<usize>,
+        number: String,
+    },
+    Unsupported {
+        path: Vec<usize>,
+        kind: &'static str,
+    },
+}
+
+fn write_path(f: &mut fmt::Formatter, path: &[usize]) -> fmt::Result {
+    write!(f, "$")?;
+    for index in path {
+        write!(f, "[{}]", index)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Syntax(err) => write!(f, "invalid JSON {}", err),
+            JsonError::NotAnInteger { path, number } => {
+                write!(f, "{} is not a 32-bit integer at ", number)?;
+                write_path(f, path)
+            }
+            JsonError::Unsupported { path, kind } => {
+                write!(f, "expected an integer or array, found {} at ", kind)?;
+                write_path(f, path)
+            }
         }
-        if next_char == &',' {
-            it.next().unwrap();
+    }
+}
+
+impl From<ParseError> for JsonError {
+    fn from(err: ParseError) -> JsonError {
+        JsonError::Syntax(err)
+    }
+}
+
+fn packet_from_json(json: &Json, path: &mut Vec<usize>) -> Result<Value, JsonError> {
+    match json {
+        Json::Number(n) if n.fract() == 0.0 && (i32::MIN as f64..=i32::MAX as f64).contains(n) => {
+            Ok(Value::Number(*n as i32))
+        }
+        Json::Number(n) => Err(JsonError::NotAnInteger {
+            path: path.clone(),
+            number: n.to_string(),
+        }),
+        Json::Array(items) => {
+            let mut vals = Vec::with_capacity(items.len());
+            for (i, item) in items.iter().enumerate() {
+                path.push(i);
+                vals.push(packet_from_json(item, path)?);
+                path.pop();
+            }
+            Ok(Value::List(vals))
         }
-        vals.push(parse_value(it));
+        other => Err(JsonError::Unsupported {
+            path: path.clone(),
+            kind: other.kind(),
+        }),
     }
-    Value::List(vals)
 }
 
-fn parse_value(it: &mut Peekable<Chars>) -> Value {
-    if let Some(next_char) = it.peek() {
-        if next_char == &'[' {
-            it.next().unwrap();
-            parse_list(it)
-        } else {
-            parse_number(it)
+impl TryFrom<&Json> for Value {
+    type Error = JsonError;
+
+    /// Accepts nested arrays of integers only.
+    /// ```
+    /// use aoc::day13::{Json, JsonError, Value};
+    /// let json = Json::Array(vec![Json::Number(1.0), Json::Array(vec![])]);
+    /// assert_eq!(Value::try_from(&json), Ok(aoc::packet![1, []]));
+    /// let json = Json::Array(vec![Json::Array(vec![Json::Null])]);
+    /// assert_eq!(Value::try_from(&json), Err(JsonError::Unsupported{path: vec![0, 0], kind: "null"}));
+    /// ```
+    fn try_from(json: &Json) -> Result<Value, JsonError> {
+        packet_from_json(json, &mut vec![])
+    }
+}
+
+impl From<&Value> for Json {
+    fn from(value: &Value) -> Json {
+        match value {
+            Value::Number(number) => Json::Number(*number as f64),
+            Value::List(items) => Json::Array(items.iter().map(Json::from).collect()),
         }
-    } else {
-        panic!("Should not happend");
     }
 }
 
+impl Value {
+    /// Reads a packet from JSON text, which unlike the puzzle syntax may
+    /// contain whitespace.
+    /// ```
+    /// use aoc::day13::Value;
+    /// assert_eq!(Value::from_json(" [ 1, [ -2 ], [] ]\n"), Ok(aoc::packet![1, [(-2)], []]));
+    /// assert_eq!(Value::from_json("[1.0]"), Ok(aoc::packet![1]));
+    /// let err = Value::from_json("[[1], [2.5]]").unwrap_err();
+    /// assert_eq!(err.to_string(), "2.5 is not a 32-bit integer at $[1][0]");
+    /// let err = Value::from_json(r#"{"packet": [1]}"#).unwrap_err();
+    /// assert_eq!(err.to_string(), "expected an integer or array, found object at $");
+    /// let err = Value::from_json("[1,").unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid JSON at 3: unexpected end of input");
+    /// ```
+    pub fn from_json(s: &str) -> Result<Value, JsonError> {
+        Value::try_from(&s.parse::<Json>()?)
+    }
+
+    /// Writes the packet as compact JSON.
+    /// ```
+    /// assert_eq!(aoc::packet![1, [(-2)], []].to_json(), "[1,[-2],[]]");
+    /// ```
+    pub fn to_json(&self) -> String {
+        Json::from(self).to_string()
+    }
+}
+
+/// Reads a JSON array of packets, e.g. the contents of a JSON file.
+/// ```
+/// use aoc::day13::{packets_from_json, packets_to_json, JsonError};
+/// let packets = packets_from_json("[\n  [1, 1, 3],\n  [[1], 4]\n]").unwrap();
+/// assert_eq!(packets, vec![aoc::packet![1, 1, 3], aoc::packet![[1], 4]]);
+/// assert_eq!(packets_to_json(&packets), "[\n[1,1,3],\n[[1],4]\n]\n");
+/// assert_eq!(packets_from_json(&packets_to_json(&packets)), Ok(packets));
+/// assert_eq!(packets_from_json("7"), Err(JsonError::Unsupported{path: vec![], kind: "number"}));
+/// ```
+pub fn packets_from_json(s: &str) -> Result<Vec<Value>, JsonError> {
+    match s.parse::<Json>()? {
+        Json::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| packet_from_json(item, &mut vec![i]))
+            .collect(),
+        other => Err(JsonError::Unsupported {
+            path: vec![],
+            kind: other.kind(),
+        }),
+    }
+}
+
+/// Writes packets as a JSON array with one packet per line.
+pub fn packets_to_json(packets: &[Value]) -> String {
+    let lines: Vec<String> = packets.iter().map(Value::to_json).collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
 pub fn run() {
     let lines = read_lines("in/day13.in").unwrap();
 
     let pairs: Vec<String> = lines.map(|x| x.unwrap()).collect();
-    let mut idx = 1;
     let mut res = 0;
 
     let mut all_packets = vec![];
-    for pair in pairs.split(|x| x.is_empty()) {
-        let first = parse_value(&mut pair[0].chars().peekable());
-        let second = parse_value(&mut pair[1].chars().peekable());
+    for (idx, pair) in (1..).zip(pairs.split(|x| x.is_empty())) {
+        let first: Value = pair[0].parse().unwrap();
+        let second: Value = pair[1].parse().unwrap();
         all_packets.push(first.clone());
         all_packets.push(second.clone());
 
         if first < second {
             res += idx;
         }
-        idx += 1;
     }
     println!("Day 13, part 1 {}", res);
 
-    let divider_1 = Value::List(vec![Value::List(vec![Value::Number(2)])]);
-    let divider_2 = Value::List(vec![Value::List(vec![Value::Number(6)])]);
+    let divider_1 = crate::packet![[2]];
+    let divider_2 = crate::packet![[6]];
     all_packets.push(divider_1.clone());
     all_packets.push(divider_2.clone());
 
@@ -1,5 +1,6 @@
 use crate::utils::read_lines;
 use std::collections::HashSet;
+use std::fmt;
 
 /// ```
 /// use aoc::day3::priority;
@@ -7,12 +8,26 @@ use std::collections::HashSet;
 /// ```
 pub fn priority(c: char) -> u32 {
     match c {
-        c if ('a'..='z').contains(&c) => 1 + c as u32 - 'a' as u32,
-        c if ('A'..='Z').contains(&c) => 27 + c as u32 - 'A' as u32,
+        c if c.is_ascii_lowercase() => 1 + c as u32 - 'a' as u32,
+        c if c.is_ascii_uppercase() => 27 + c as u32 - 'A' as u32,
         _ => panic!("Non-digit ASCII char given: {}", c),
     }
 }
 
+/// Inverse of `priority`.
+/// ```
+/// use aoc::day3::item;
+/// assert_eq!(item(16), 'p');
+/// assert_eq!(item(27), 'A');
+/// ```
+pub fn item(priority: u32) -> char {
+    match priority {
+        1..=26 => (b'a' + (priority - 1) as u8) as char,
+        27..=52 => (b'A' + (priority - 27) as u8) as char,
+        _ => panic!("Priority out of range: {}", priority),
+    }
+}
+
 /// ```
 /// use aoc::day3::find_bad_item;
 /// assert_eq!(find_bad_item(&[1,2,3,4,4,5,6,4]), 4);
@@ -23,6 +38,157 @@ pub fn find_bad_item(rucksack: &[u32]) -> u32 {
     **part1.intersection(&part2).next().unwrap()
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grouping {
+    /// Consecutive, non-overlapping groups.
+    Chunked,
+    /// Every window of `size` consecutive rucksacks.
+    Sliding,
+}
+
+/// Splits the rucksacks into groups of `size`. Rucksacks left over that
+/// cannot form a full group are reported as an error.
+/// ```
+/// use aoc::day3::{groups, BadgeError, Grouping};
+/// let rucksacks = vec![vec![1], vec![2], vec![3], vec![4]];
+/// assert_eq!(groups(&rucksacks, 2, Grouping::Chunked).unwrap().len(), 2);
+/// assert_eq!(groups(&rucksacks, 2, Grouping::Sliding).unwrap().len(), 3);
+/// assert_eq!(
+///     groups(&rucksacks, 3, Grouping::Chunked),
+///     Err(BadgeError::IncompleteGroup{start: 3, len: 1})
+/// );
+/// assert_eq!(
+///     groups(&rucksacks, 5, Grouping::Sliding),
+///     Err(BadgeError::IncompleteGroup{start: 0, len: 4})
+/// );
+/// assert_eq!(groups(&rucksacks, 0, Grouping::Chunked), Err(BadgeError::ZeroGroupSize));
+/// ```
+pub fn groups(
+    rucksacks: &[Vec<u32>],
+    size: usize,
+    grouping: Grouping,
+) -> Result<Vec<&[Vec<u32>]>, BadgeError> {
+    if size == 0 {
+        return Err(BadgeError::ZeroGroupSize);
+    }
+    let chunks = rucksacks.chunks_exact(size);
+    let leftover = chunks.remainder().len();
+    match grouping {
+        Grouping::Chunked if leftover > 0 => Err(BadgeError::IncompleteGroup {
+            start: rucksacks.len() - leftover,
+            len: leftover,
+        }),
+        Grouping::Chunked => Ok(chunks.collect()),
+        Grouping::Sliding if rucksacks.len() < size && !rucksacks.is_empty() => {
+            Err(BadgeError::IncompleteGroup {
+                start: 0,
+                len: rucksacks.len(),
+            })
+        }
+        Grouping::Sliding => Ok(rucksacks.windows(size).collect()),
+    }
+}
+
+/// Items (as priorities) present in every rucksack of the group, sorted.
+/// ```
+/// use aoc::day3::common_items;
+/// assert_eq!(common_items(&[vec![1, 2, 3, 3], vec![3, 2, 5], vec![2, 3]]), vec![2, 3]);
+/// assert_eq!(common_items(&[]), vec![]);
+/// ```
+pub fn common_items(group: &[Vec<u32>]) -> Vec<u32> {
+    let mut common: Vec<u32> = group
+        .iter()
+        .map(|rucksack| -> HashSet<u32> { rucksack.iter().cloned().collect() })
+        .reduce(|acc, elem| acc.intersection(&elem).cloned().collect())
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    common.sort();
+    common
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BadgeError {
+    NoCommonItem,
+    MultipleCommonItems(Vec<u32>),
+    /// `len` rucksacks from index `start` do not make up a full group.
+    IncompleteGroup {
+        start: usize,
+        len: usize,
+    },
+    ZeroGroupSize,
+}
+
+impl fmt::Display for BadgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BadgeError::NoCommonItem => write!(f, "no item common to the group"),
+            BadgeError::MultipleCommonItems(items) => {
+                let items: String = items.iter().map(|&p| item(p)).collect();
+                write!(f, "several items common to the group: {}", items)
+            }
+            BadgeError::IncompleteGroup { start, len } => {
+                write!(
+                    f,
+                    "{} rucksacks from {} do not make a full group",
+                    len, start
+                )
+            }
+            BadgeError::ZeroGroupSize => write!(f, "group size must be positive"),
+        }
+    }
+}
+
+/// ```
+/// use aoc::day3::{find_badge, BadgeError};
+/// assert_eq!(find_badge(&[vec![1, 2], vec![2, 3]]), Ok(2));
+/// assert_eq!(find_badge(&[vec![1], vec![2]]), Err(BadgeError::NoCommonItem));
+/// assert_eq!(
+///     find_badge(&[vec![1, 2], vec![2, 1]]),
+///     Err(BadgeError::MultipleCommonItems(vec![1, 2]))
+/// );
+/// ```
+pub fn find_badge(group: &[Vec<u32>]) -> Result<u32, BadgeError> {
+    let common = common_items(group);
+    match common.len() {
+        0 => Err(BadgeError::NoCommonItem),
+        1 => Ok(common[0]),
+        _ => Err(BadgeError::MultipleCommonItems(common)),
+    }
+}
+
+/// A `BadgeError` in the group at `index`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GroupError {
+    pub index: usize,
+    pub error: BadgeError,
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "group {}: {}", self.index, self.error)
+    }
+}
+
+/// Badge of every group, failing on the first group without exactly one.
+/// ```
+/// use aoc::day3::{find_badges, BadgeError, GroupError};
+/// let ok = [vec![1, 2], vec![2, 3]];
+/// let bad = [vec![1], vec![2]];
+/// assert_eq!(find_badges(&[&ok, &ok]), Ok(vec![2, 2]));
+/// assert_eq!(
+///     find_badges(&[&ok, &bad]),
+///     Err(GroupError{index: 1, error: BadgeError::NoCommonItem})
+/// );
+/// ```
+pub fn find_badges(groups: &[&[Vec<u32>]]) -> Result<Vec<u32>, GroupError> {
+    groups
+        .iter()
+        .enumerate()
+        .map(|(index, group)| find_badge(group).map_err(|error| GroupError { index, error }))
+        .collect()
+}
+
 pub fn run() {
     let lines = read_lines("in/day3.in").unwrap();
 
@@ -37,21 +203,7 @@ pub fn run() {
         rucksacks.iter().map(|x| find_bad_item(x)).sum::<u32>()
     );
 
-    let groups = rucksacks.chunks(3);
-
-    let mut priorities_of_groups = vec![];
-    for group in groups {
-        priorities_of_groups.push(
-            *group
-                .iter()
-                .cloned()
-                .map(|rucksack| -> HashSet<u32> { HashSet::from_iter(rucksack) })
-                .reduce(|acc, elem| acc.intersection(&elem).into_iter().cloned().collect())
-                .unwrap()
-                .iter()
-                .next()
-                .unwrap(),
-        );
-    }
+    let groups = groups(&rucksacks, 3, Grouping::Chunked).unwrap();
+    let priorities_of_groups = find_badges(&groups).unwrap();
     println!("Day 3.2: {}", priorities_of_groups.into_iter().sum::<u32>(),);
 }
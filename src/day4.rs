@@ -1,5 +1,7 @@
 use crate::utils::read_lines;
+use std::collections::BTreeMap;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Elf {
     pub start: i32,
     pub end: i32,
@@ -27,6 +29,152 @@ pub fn overlaps(r1: &Elf, r2: &Elf) -> bool {
         || contains(r1, r2)
 }
 
+/// Elves with `start <= end`, by index. An inverted range is assigned no
+/// sections, so every analysis below ignores it.
+fn assigned(elves: &[Elf]) -> impl Iterator<Item = (usize, &Elf)> {
+    elves
+        .iter()
+        .enumerate()
+        .filter(|(_, elf)| elf.start <= elf.end)
+}
+
+/// Maximal runs of sections covered by the same number of elves, as
+/// `(start, end, depth)` with inclusive bounds. Uncovered gaps are omitted.
+/// ```
+/// use aoc::day4::{coverage_segments, Elf};
+/// let elves = [Elf{start: 1, end: 4}, Elf{start: 3, end: 6}, Elf{start: 9, end: 9}];
+/// assert_eq!(
+///     coverage_segments(&elves),
+///     vec![(1, 2, 1), (3, 4, 2), (5, 6, 1), (9, 9, 1)]
+/// );
+/// let touching = [Elf{start: 1, end: 2}, Elf{start: 3, end: 4}];
+/// assert_eq!(coverage_segments(&touching), vec![(1, 4, 1)]);
+/// assert_eq!(
+///     coverage_segments(&[Elf{start: i32::MAX - 1, end: i32::MAX}]),
+///     vec![(i32::MAX - 1, i32::MAX, 1)]
+/// );
+/// ```
+pub fn coverage_segments(elves: &[Elf]) -> Vec<(i32, i32, usize)> {
+    // Keyed by i64 so the event after a section ending at i32::MAX fits.
+    let mut events: BTreeMap<i64, i64> = BTreeMap::new();
+    for (_, elf) in assigned(elves) {
+        *events.entry(elf.start as i64).or_default() += 1;
+        *events.entry(elf.end as i64 + 1).or_default() -= 1;
+    }
+    // An elf ending right before another starts leaves a zero delta, which
+    // would otherwise split a run.
+    events.retain(|_, delta| *delta != 0);
+
+    let mut segments = vec![];
+    let mut depth = 0;
+    let mut positions = events.iter().peekable();
+    while let Some((&pos, &delta)) = positions.next() {
+        depth += delta;
+        if depth > 0 {
+            let end = positions.peek().unwrap().0 - 1;
+            segments.push((pos as i32, end as i32, depth as usize));
+        }
+    }
+    segments
+}
+
+/// Number of elves assigned to each covered section ID.
+/// ```
+/// use aoc::day4::{coverage, Elf};
+/// let c = coverage(&[Elf{start: 2, end: 4}, Elf{start: 4, end: 5}]);
+/// assert_eq!(c.get(&2), Some(&1));
+/// assert_eq!(c.get(&4), Some(&2));
+/// assert_eq!(c.get(&6), None);
+/// ```
+pub fn coverage(elves: &[Elf]) -> BTreeMap<i32, usize> {
+    coverage_segments(elves)
+        .into_iter()
+        .flat_map(|(start, end, depth)| (start..=end).map(move |section| (section, depth)))
+        .collect()
+}
+
+/// Largest number of elves assigned to the same section.
+/// ```
+/// use aoc::day4::{max_overlap_depth, Elf};
+/// let elves = [Elf{start: 1, end: 3}, Elf{start: 2, end: 5}, Elf{start: 3, end: 3}];
+/// assert_eq!(max_overlap_depth(&elves), 3);
+/// assert_eq!(max_overlap_depth(&[]), 0);
+/// ```
+pub fn max_overlap_depth(elves: &[Elf]) -> usize {
+    coverage_segments(elves)
+        .into_iter()
+        .map(|(_, _, depth)| depth)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Groups elves (by index) into clusters connected through overlapping
+/// assignments. Clusters are ordered by their first section.
+/// ```
+/// use aoc::day4::{overlap_clusters, Elf};
+/// let elves = [
+///     Elf{start: 5, end: 7},
+///     Elf{start: 1, end: 2},
+///     Elf{start: 7, end: 9},
+///     Elf{start: 3, end: 3},
+/// ];
+/// assert_eq!(overlap_clusters(&elves), vec![vec![1], vec![3], vec![0, 2]]);
+/// let inverted = [Elf{start: 1, end: 5}, Elf{start: 4, end: 2}];
+/// assert_eq!(overlap_clusters(&inverted), vec![vec![0]]);
+/// ```
+pub fn overlap_clusters(elves: &[Elf]) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = assigned(elves).map(|(i, _)| i).collect();
+    order.sort_by_key(|&i| (elves[i].start, elves[i].end));
+
+    let mut clusters: Vec<Vec<usize>> = vec![];
+    let mut cluster_end = i32::MIN;
+    for i in order {
+        match clusters.last_mut() {
+            Some(cluster) if elves[i].start <= cluster_end => {
+                cluster.push(i);
+                cluster_end = cluster_end.max(elves[i].end);
+            }
+            _ => {
+                clusters.push(vec![i]);
+                cluster_end = elves[i].end;
+            }
+        }
+    }
+    for cluster in clusters.iter_mut() {
+        cluster.sort();
+    }
+    clusters
+}
+
+/// Indices of elves whose every section is also assigned to some other elf.
+/// ```
+/// use aoc::day4::{redundant_elves, Elf};
+/// let elves = [Elf{start: 1, end: 3}, Elf{start: 2, end: 5}, Elf{start: 4, end: 6}];
+/// assert_eq!(redundant_elves(&elves), vec![1]);
+/// let inverted = [Elf{start: 1, end: 5}, Elf{start: 4, end: 2}];
+/// assert!(redundant_elves(&inverted).is_empty());
+/// ```
+pub fn redundant_elves(elves: &[Elf]) -> Vec<usize> {
+    let segments = coverage_segments(elves);
+    assigned(elves)
+        .filter(|&(_, elf)| {
+            let first = segments.partition_point(|&(_, end, _)| end < elf.start);
+            let mut next = elf.start;
+            for &(start, end, depth) in &segments[first..] {
+                if start > next || depth < 2 {
+                    return false;
+                }
+                if end >= elf.end {
+                    return true;
+                }
+                next = end + 1;
+            }
+            false
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
 pub fn run() {
     let lines = read_lines("in/day4.in").unwrap();
 
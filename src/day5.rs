@@ -1,93 +1,499 @@
 use crate::utils::read_lines;
-use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
 
-struct Move {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
     pub from: u32,
     pub to: u32,
     pub how_many: usize,
 }
 
-fn part1(mut crates: HashMap<u32, Vec<char>>, instructions: &[Move]) -> HashMap<u32, Vec<char>> {
-    for instruction in instructions {
-        let mut from = crates.get(&instruction.from).unwrap().clone();
-        let mut to = crates.get(&instruction.to).unwrap().clone();
-        to.extend(from.drain(from.len() - instruction.how_many..).rev());
-        crates.insert(instruction.from, from);
-        crates.insert(instruction.to, to);
+#[derive(Debug, PartialEq, Eq)]
+pub enum CraneError {
+    /// The crate drawing could not be parsed; `line` is 1-based.
+    InvalidDrawing { line: usize, reason: String },
+    /// A `move N from A to B` line could not be parsed.
+    InvalidInstruction(String),
+    /// A move refers to a stack label that is not in the drawing footer.
+    UnknownStack(u32),
+    /// A move takes more crates than the source stack holds.
+    NotEnoughCrates {
+        stack: u32,
+        requested: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for CraneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CraneError::InvalidDrawing { line, reason } => {
+                write!(f, "invalid crate drawing on line {}: {}", line, reason)
+            }
+            CraneError::InvalidInstruction(instruction) => {
+                write!(f, "invalid instruction: {:?}", instruction)
+            }
+            CraneError::UnknownStack(stack) => write!(f, "unknown stack {}", stack),
+            CraneError::NotEnoughCrates {
+                stack,
+                requested,
+                available,
+            } => write!(
+                f,
+                "cannot move {} crates from stack {} holding {}",
+                requested, stack, available
+            ),
+        }
     }
-    crates
 }
 
-fn part2(mut crates: HashMap<u32, Vec<char>>, instructions: &[Move]) -> HashMap<u32, Vec<char>> {
-    for instruction in instructions {
-        {
-            // TODO: Improve with split_at_mut to avoid clone in the line below.
-            let from = crates.get(&instruction.from).unwrap().clone();
-            let to = crates.get_mut(&instruction.to).unwrap();
-            to.extend(&from[from.len() - instruction.how_many..from.len()]);
+/// Crate stacks stored in a `Vec`, ordered by their footer label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stacks {
+    labels: Vec<u32>,
+    stacks: Vec<Vec<char>>,
+    /// Whether `labels` is `labels[0], labels[0] + 1, ...`, so lookups are offsets.
+    contiguous: bool,
+}
+
+impl Stacks {
+    /// ```
+    /// use aoc::day5::Stacks;
+    /// let stacks = Stacks::new(vec![(3, vec!['P']), (1, vec!['Z', 'N'])]);
+    /// assert_eq!(stacks.labels(), &[1, 3]);
+    /// assert_eq!(stacks[3], ['P']);
+    /// assert_eq!(stacks.get(2), None);
+    /// ```
+    pub fn new(mut stacks: Vec<(u32, Vec<char>)>) -> Stacks {
+        stacks.sort_by_key(|(label, _)| *label);
+        let (labels, stacks): (Vec<u32>, Vec<Vec<char>>) = stacks.into_iter().unzip();
+        assert!(
+            labels.windows(2).all(|w| w[0] != w[1]),
+            "Duplicate stack label"
+        );
+        let contiguous = labels
+            .iter()
+            .enumerate()
+            .all(|(i, &label)| label as u64 == labels[0] as u64 + i as u64);
+        Stacks {
+            labels,
+            stacks,
+            contiguous,
         }
+    }
 
-        let from = crates.get_mut(&instruction.from).unwrap();
-        from.truncate(from.len() - instruction.how_many);
+    pub fn labels(&self) -> &[u32] {
+        &self.labels
+    }
+
+    pub fn get(&self, label: u32) -> Option<&[char]> {
+        self.position(label).map(|i| self.stacks[i].as_slice())
     }
-    crates
-}
 
-pub fn what_is_on_top(crates: HashMap<u32, Vec<char>>) -> String {
-    let mut res: String = "".to_string();
-    for i in 1..10 {
-        if let Some(top) = crates.get(&i).unwrap().last() {
-            res.push(*top);
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &[char])> {
+        self.labels
+            .iter()
+            .cloned()
+            .zip(self.stacks.iter().map(|stack| stack.as_slice()))
+    }
+
+    fn position(&self, label: u32) -> Option<usize> {
+        if self.contiguous {
+            let i = label.checked_sub(*self.labels.first()?)? as usize;
+            (i < self.labels.len()).then_some(i)
         } else {
-            res.push(' ');
+            self.labels.binary_search(&label).ok()
         }
     }
-    res
-}
 
-pub fn run() {
-    let lines = read_lines("in/day5.in").unwrap();
-    let input: Vec<String> = lines.map(|line| line.unwrap()).collect();
+    /// Moves the top `instruction.how_many` crates, then reverses each lift
+    /// of `capacity` crates counted from the top of the moved block. This
+    /// covers every crane: capacity 1 reverses the block, unbounded keeps it.
+    fn move_crates(&mut self, instruction: &Move, capacity: usize) -> Result<(), CraneError> {
+        let to = self
+            .position(instruction.to)
+            .ok_or(CraneError::UnknownStack(instruction.to))?;
+        let from = self
+            .position(instruction.from)
+            .ok_or(CraneError::UnknownStack(instruction.from))?;
+        let available = self.stacks[from].len();
+        let how_many = instruction.how_many;
+        if available < how_many {
+            return Err(CraneError::NotEnoughCrates {
+                stack: instruction.from,
+                requested: how_many,
+                available,
+            });
+        }
 
-    let mut slices = input.split(|line| line.is_empty());
-    let mut crates_in = slices.next().unwrap();
-    let instructions_in = slices.next().unwrap();
+        // Crates put back onto the stack they came from end up where they
+        // were, whatever the crane.
+        if from == to {
+            return Ok(());
+        }
+        let (source, target) = if from < to {
+            let (left, right) = self.stacks.split_at_mut(to);
+            (&mut left[from], &mut right[0])
+        } else {
+            let (left, right) = self.stacks.split_at_mut(from);
+            (&mut right[0], &mut left[to])
+        };
+        target.extend_from_slice(&source[available - how_many..]);
+        source.truncate(available - how_many);
+
+        if capacity < how_many {
+            let target = &mut self.stacks[to];
+            let len = target.len();
+            let block = &mut target[len - how_many..];
+            block.reverse();
+            for lift in block.chunks_mut(capacity) {
+                lift.reverse();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Index<u32> for Stacks {
+    type Output = [char];
 
-    crates_in = &crates_in[0..crates_in.len() - 1];
+    fn index(&self, label: u32) -> &[char] {
+        self.get(label)
+            .unwrap_or_else(|| panic!("No stack labelled {}", label))
+    }
+}
 
-    let mut crates: HashMap<u32, Vec<char>> = HashMap::new();
+/// Parses the crate drawing, including the numbered footer line. Stack count
+/// and labels are taken from the footer; each crate belongs to the label
+/// written underneath it.
+/// ```
+/// use aoc::day5::parse_drawing;
+/// let drawing = vec![
+///     "    [D]".to_string(),
+///     "[N] [C]".to_string(),
+///     " 1   2   3".to_string(),
+/// ];
+/// let crates = parse_drawing(&drawing).unwrap();
+/// assert_eq!(crates[1], ['N']);
+/// assert_eq!(crates[2], ['C', 'D']);
+/// assert!(crates[3].is_empty());
+///
+/// let wide = vec!["[A] [B]".to_string(), " 9  10".to_string()];
+/// assert_eq!(parse_drawing(&wide).unwrap()[10], ['B']);
+/// ```
+pub fn parse_drawing(lines: &[String]) -> Result<Stacks, CraneError> {
+    let (footer, rows) = lines.split_last().ok_or(CraneError::InvalidDrawing {
+        line: 1,
+        reason: "drawing is empty".to_string(),
+    })?;
+    let footer_line = lines.len();
 
-    for l in crates_in.iter().rev() {
-        let mut idx = 1;
-        let mut stack = 1;
-        while idx < l.len() {
-            let current_crate = l.chars().nth(idx).unwrap();
-            if current_crate != ' ' {
-                crates
-                    .entry(stack)
-                    .and_modify(|s| s.push(current_crate))
-                    .or_insert_with(|| vec![current_crate]);
+    // (first column, last column, label) for every number in the footer.
+    let mut labels: Vec<(usize, usize, u32)> = vec![];
+    let chars: Vec<char> = footer.chars().collect();
+    let mut idx = 0;
+    while idx < chars.len() {
+        if chars[idx].is_ascii_digit() {
+            let start = idx;
+            while idx < chars.len() && chars[idx].is_ascii_digit() {
+                idx += 1;
+            }
+            let label = chars[start..idx]
+                .iter()
+                .collect::<String>()
+                .parse::<u32>()
+                .map_err(|e| CraneError::InvalidDrawing {
+                    line: footer_line,
+                    reason: e.to_string(),
+                })?;
+            if labels.iter().any(|&(_, _, l)| l == label) {
+                return Err(CraneError::InvalidDrawing {
+                    line: footer_line,
+                    reason: format!("duplicate stack label {}", label),
+                });
             }
+            labels.push((start, idx - 1, label));
+        } else if chars[idx] == ' ' {
+            idx += 1;
+        } else {
+            return Err(CraneError::InvalidDrawing {
+                line: footer_line,
+                reason: format!("unexpected character {:?} in stack labels", chars[idx]),
+            });
+        }
+    }
+    if labels.is_empty() {
+        return Err(CraneError::InvalidDrawing {
+            line: footer_line,
+            reason: "no stack labels".to_string(),
+        });
+    }
 
-            stack += 1;
-            idx += 4;
+    let mut crates: Vec<Vec<char>> = vec![vec![]; labels.len()];
+
+    for (row_idx, row) in rows.iter().enumerate().rev() {
+        let line = row_idx + 1;
+        let chars: Vec<char> = row.chars().collect();
+        let mut idx = 0;
+        while idx < chars.len() {
+            match chars[idx] {
+                ' ' => idx += 1,
+                '[' if idx + 2 < chars.len() && chars[idx + 2] == ']' => {
+                    let column = idx + 1;
+                    let stack = labels
+                        .iter()
+                        .position(|&(start, end, _)| start <= column + 1 && column <= end + 1)
+                        .ok_or_else(|| CraneError::InvalidDrawing {
+                            line,
+                            reason: format!("crate in column {} is not above a label", column),
+                        })?;
+                    crates[stack].push(chars[column]);
+                    idx += 3;
+                }
+                c => {
+                    return Err(CraneError::InvalidDrawing {
+                        line,
+                        reason: format!("unexpected character {:?} in column {}", c, idx),
+                    })
+                }
+            }
         }
     }
+    Ok(Stacks::new(
+        labels
+            .into_iter()
+            .map(|(_, _, label)| label)
+            .zip(crates)
+            .collect(),
+    ))
+}
+
+/// ```
+/// use aoc::day5::{parse_move, Move};
+/// assert_eq!(parse_move("move 12 from 10 to 3"), Ok(Move{how_many: 12, from: 10, to: 3}));
+/// assert!(parse_move("move three from 1 to 2").is_err());
+/// ```
+pub fn parse_move(instruction: &str) -> Result<Move, CraneError> {
+    scan!(instruction; ("move", let how_many: usize, "from", let from:u32, "to", let to: u32) => {
+        Move{from, to, how_many}
+    })
+    .map_err(|_| CraneError::InvalidInstruction(instruction.to_string()))
+}
+
+/// A crane model: how a single `Move` rearranges the stacks.
+pub trait Crane {
+    fn apply(&self, crates: &mut Stacks, instruction: &Move) -> Result<(), CraneError>;
+}
+
+/// Moves crates one at a time, reversing their order.
+pub struct CrateMover9000;
+
+/// Moves all crates of an instruction in a single lift, keeping their order.
+pub struct CrateMover9001;
+
+/// Lifts at most `capacity` crates at a time; each lift keeps its order.
+pub struct LimitedCrane {
+    capacity: usize,
+}
+
+impl LimitedCrane {
+    pub fn new(capacity: usize) -> LimitedCrane {
+        assert!(capacity > 0, "Crane capacity must be positive");
+        LimitedCrane { capacity }
+    }
+}
+
+impl Crane for CrateMover9000 {
+    fn apply(&self, crates: &mut Stacks, instruction: &Move) -> Result<(), CraneError> {
+        crates.move_crates(instruction, 1)
+    }
+}
+
+impl Crane for CrateMover9001 {
+    fn apply(&self, crates: &mut Stacks, instruction: &Move) -> Result<(), CraneError> {
+        crates.move_crates(instruction, usize::MAX)
+    }
+}
+
+impl Crane for LimitedCrane {
+    fn apply(&self, crates: &mut Stacks, instruction: &Move) -> Result<(), CraneError> {
+        crates.move_crates(instruction, self.capacity)
+    }
+}
+
+/// ```
+/// use aoc::day5::{operate, LimitedCrane, Move, Stacks};
+/// let crates = Stacks::new(vec![(1, vec!['A', 'B', 'C', 'D', 'E']), (2, vec![])]);
+/// let moved = operate(&LimitedCrane::new(2), crates, &[Move{from: 1, to: 2, how_many: 5}]);
+/// assert_eq!(moved.unwrap()[2], ['D', 'E', 'B', 'C', 'A']);
+/// ```
+pub fn operate<C: Crane>(
+    crane: &C,
+    mut crates: Stacks,
+    instructions: &[Move],
+) -> Result<Stacks, CraneError> {
+    for instruction in instructions {
+        crane.apply(&mut crates, instruction)?;
+    }
+    Ok(crates)
+}
+
+/// ```
+/// use aoc::day5::{part1, CraneError, Move, Stacks};
+/// let crates = Stacks::new(vec![(1, vec!['A', 'B']), (2, vec![])]);
+/// let moved = part1(crates.clone(), &[Move{from: 1, to: 2, how_many: 2}]).unwrap();
+/// assert_eq!(moved[2], ['B', 'A']);
+/// let in_place = part1(crates.clone(), &[Move{from: 1, to: 1, how_many: 2}]).unwrap();
+/// assert_eq!(in_place[1], ['A', 'B']);
+/// assert_eq!(
+///     part1(crates, &[Move{from: 2, to: 1, how_many: 1}]).err(),
+///     Some(CraneError::NotEnoughCrates{stack: 2, requested: 1, available: 0})
+/// );
+/// ```
+pub fn part1(crates: Stacks, instructions: &[Move]) -> Result<Stacks, CraneError> {
+    operate(&CrateMover9000, crates, instructions)
+}
 
-    let mut instructions = vec![];
-    for instruction in instructions_in {
-        scan!(instruction; ("move", let how_many: usize, "from", let from:u32, "to", let to: u32) => {
-            instructions.push(Move{from, to,  how_many});
+/// ```
+/// use aoc::day5::{part2, CraneError, Move, Stacks};
+/// let crates = Stacks::new(vec![(1, vec!['A', 'B']), (2, vec![])]);
+/// let moved = part2(crates.clone(), &[Move{from: 1, to: 2, how_many: 2}]).unwrap();
+/// assert_eq!(moved[2], ['A', 'B']);
+/// assert_eq!(
+///     part2(crates, &[Move{from: 1, to: 3, how_many: 1}]).err(),
+///     Some(CraneError::UnknownStack(3))
+/// );
+/// ```
+pub fn part2(crates: Stacks, instructions: &[Move]) -> Result<Stacks, CraneError> {
+    operate(&CrateMover9001, crates, instructions)
+}
+
+/// Iterator over the stack state after each instruction. Stops after the
+/// first instruction that fails.
+pub struct Replay<'a, C: Crane> {
+    crane: &'a C,
+    crates: Stacks,
+    instructions: std::slice::Iter<'a, Move>,
+    failed: bool,
+}
+
+impl<'a, C: Crane> Iterator for Replay<'a, C> {
+    type Item = Result<Stacks, CraneError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let instruction = self.instructions.next()?;
+        match self.crane.apply(&mut self.crates, instruction) {
+            Ok(()) => Some(Ok(self.crates.clone())),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
         }
-        ).unwrap();
     }
+}
+
+/// ```
+/// use aoc::day5::{replay, CrateMover9001, Move, Stacks};
+/// let crates = Stacks::new(vec![(1, vec!['A', 'B']), (2, vec![])]);
+/// let moves = [Move{from: 1, to: 2, how_many: 1}, Move{from: 2, to: 1, how_many: 2}];
+/// let states: Vec<_> = replay(&CrateMover9001, crates, &moves).collect();
+/// assert_eq!(states.len(), 2);
+/// assert_eq!(states[0].as_ref().unwrap()[2], ['B']);
+/// assert!(states[1].is_err());
+/// ```
+pub fn replay<'a, C: Crane>(
+    crane: &'a C,
+    crates: Stacks,
+    instructions: &'a [Move],
+) -> Replay<'a, C> {
+    Replay {
+        crane,
+        crates,
+        instructions: instructions.iter(),
+        failed: false,
+    }
+}
+
+/// Draws the stacks in the puzzle's format, labels in ascending order.
+/// `parse_drawing` reads the result back.
+/// ```
+/// use aoc::day5::{parse_drawing, render_drawing, Stacks};
+/// let crates = Stacks::new(vec![(1, vec!['Z', 'N']), (2, vec!['M', 'C', 'D']), (3, vec!['P'])]);
+/// let drawing = render_drawing(&crates);
+/// assert_eq!(drawing, "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3");
+/// let lines: Vec<String> = drawing.lines().map(String::from).collect();
+/// assert_eq!(parse_drawing(&lines).unwrap(), crates);
+/// ```
+pub fn render_drawing(crates: &Stacks) -> String {
+    let labels = crates.labels();
+    let cell_width = labels
+        .iter()
+        .map(|label| label.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    let height = crates
+        .iter()
+        .map(|(_, stack)| stack.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![];
+    for level in (0..height).rev() {
+        let line: Vec<String> = labels
+            .iter()
+            .map(|&label| match crates[label].get(level) {
+                Some(c) => format!("{:<cell_width$}", format!("[{}]", c)),
+                None => " ".repeat(cell_width),
+            })
+            .collect();
+        lines.push(line.join(" ").trim_end().to_string());
+    }
+    let footer: Vec<String> = labels
+        .iter()
+        .map(|label| format!(" {:<cell_width$}", label))
+        .collect();
+    lines.push(footer.join("").trim_end().to_string());
+    lines.join("\n")
+}
+
+/// Top crate of every stack in label order, with a space for empty stacks.
+/// ```
+/// use aoc::day5::{what_is_on_top, Stacks};
+/// let crates = Stacks::new(vec![(10, vec!['Z']), (2, vec![]), (1, vec!['A', 'B'])]);
+/// assert_eq!(what_is_on_top(&crates), "B Z");
+/// ```
+pub fn what_is_on_top(crates: &Stacks) -> String {
+    crates
+        .iter()
+        .map(|(_, stack)| *stack.last().unwrap_or(&' '))
+        .collect()
+}
+
+pub fn run() {
+    let lines = read_lines("in/day5.in").unwrap();
+    let input: Vec<String> = lines.map(|line| line.unwrap()).collect();
+
+    let mut slices = input.split(|line| line.is_empty());
+    let crates_in = slices.next().unwrap();
+    let instructions_in = slices.next().unwrap();
+
+    let crates = parse_drawing(crates_in).unwrap();
+    let instructions: Vec<Move> = instructions_in
+        .iter()
+        .map(|instruction| parse_move(instruction).unwrap())
+        .collect();
 
     println!(
         "Day 5, part 1: {}",
-        what_is_on_top(part1(crates.clone(), &instructions))
+        what_is_on_top(&part1(crates.clone(), &instructions).unwrap())
     );
     println!(
         "Day 5, part 2: {}",
-        what_is_on_top(part2(crates.clone(), &instructions))
+        what_is_on_top(&part2(crates, &instructions).unwrap())
     );
 }
@@ -1,34 +1,262 @@
 use crate::utils::read_lines;
-use std::collections::HashSet;
+use std::io::{self, BufReader, Read};
+
+/// Incremental start-of-packet detector. Remembers where each byte value was
+/// last seen, so every byte is processed in constant time.
+pub struct MarkerDetector {
+    window_size: usize,
+    /// 1-based position of the last occurrence of each byte, 0 if unseen.
+    last_seen: [usize; 256],
+    /// 1-based position where the current run of distinct bytes starts.
+    run_start: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(window_size: usize) -> MarkerDetector {
+        assert!(window_size > 0, "Window size must be positive");
+        MarkerDetector {
+            window_size,
+            last_seen: [0; 256],
+            run_start: 1,
+            position: 0,
+        }
+    }
+
+    /// Consumes one byte. Returns the number of bytes consumed so far if the
+    /// last `window_size` of them are all different.
+    /// ```
+    /// use aoc::day6::MarkerDetector;
+    /// let mut detector = MarkerDetector::new(3);
+    /// let found: Vec<_> = "abac".bytes().map(|b| detector.push(b)).collect();
+    /// assert_eq!(found, vec![None, None, None, Some(4)]);
+    /// ```
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+        let previous = &mut self.last_seen[byte as usize];
+        self.run_start = self.run_start.max(*previous + 1);
+        *previous = self.position;
+        (self.run_len() >= self.window_size).then_some(self.position)
+    }
+
+    /// Consumes a byte that can never be part of a marker.
+    fn skip(&mut self) {
+        self.position += 1;
+        self.run_start = self.position + 1;
+    }
+
+    /// Length of the run of distinct bytes ending at the current position.
+    fn run_len(&self) -> usize {
+        self.position + 1 - self.run_start
+    }
+}
+
+/// Iterator over every marker position in a byte stream.
+pub struct Markers<I: Iterator<Item = u8>> {
+    bytes: I,
+    detector: MarkerDetector,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Markers<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        for byte in self.bytes.by_ref() {
+            if let Some(position) = self.detector.push(byte) {
+                return Some(position);
+            }
+        }
+        None
+    }
+}
+
+/// ```
+/// use aoc::day6::markers;
+/// let all: Vec<usize> = markers("abcabdd".bytes(), 3).collect();
+/// assert_eq!(all, vec![3, 4, 5, 6]);
+/// ```
+pub fn markers<I: IntoIterator<Item = u8>>(bytes: I, window_size: usize) -> Markers<I::IntoIter> {
+    Markers {
+        bytes: bytes.into_iter(),
+        detector: MarkerDetector::new(window_size),
+    }
+}
+
+/// Reads until the first marker; never buffers more than the reader does.
+/// ```
+/// use aoc::day6::find_marker;
+/// assert_eq!(find_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb".as_bytes(), 14).unwrap(), Some(19));
+/// assert_eq!(find_marker("aaaa".as_bytes(), 2).unwrap(), None);
+/// ```
+pub fn find_marker<R: Read>(reader: R, window_size: usize) -> io::Result<Option<usize>> {
+    let mut detector = MarkerDetector::new(window_size);
+    for byte in BufReader::new(reader).bytes() {
+        if let Some(position) = detector.push(byte?) {
+            return Ok(Some(position));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    Packet,
+    Message,
+}
+
+pub struct ProtocolConfig {
+    pub packet_marker: usize,
+    pub message_marker: usize,
+    /// Bytes that may appear in a marker; `None` allows every byte.
+    pub alphabet: Option<Vec<u8>>,
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> ProtocolConfig {
+        ProtocolConfig {
+            packet_marker: 4,
+            message_marker: 14,
+            alphabet: Some((b'a'..=b'z').collect()),
+        }
+    }
+}
+
+impl ProtocolConfig {
+    fn marker_len(&self, kind: FrameKind) -> usize {
+        match kind {
+            FrameKind::Packet => self.packet_marker,
+            FrameKind::Message => self.message_marker,
+        }
+    }
+}
+
+/// A marker and the payload that follows it up to the next marker.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub kind: FrameKind,
+    /// Offset of the first byte of the marker.
+    pub offset: usize,
+    pub payload_offset: usize,
+    pub payload: &'a [u8],
+}
+
+/// Splits a datastream into frames. A run of distinct bytes that is long
+/// enough for both markers is read as the longer one. Bytes before the first
+/// marker are dropped.
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    config: ProtocolConfig,
+    allowed: [bool; 256],
+    next_marker: Option<(usize, FrameKind)>,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8], config: ProtocolConfig) -> Decoder<'a> {
+        assert!(
+            config.packet_marker > 0 && config.message_marker > 0,
+            "Marker lengths must be positive"
+        );
+        assert_ne!(
+            config.packet_marker, config.message_marker,
+            "Packet and message markers must differ in length"
+        );
+        let mut allowed = [config.alphabet.is_none(); 256];
+        for &byte in config.alphabet.iter().flatten() {
+            allowed[byte as usize] = true;
+        }
+        let mut decoder = Decoder {
+            data,
+            config,
+            allowed,
+            next_marker: None,
+        };
+        decoder.next_marker = decoder.find_marker(0);
+        decoder
+    }
+
+    /// Start and kind of the first marker beginning at or after `from`.
+    fn find_marker(&self, from: usize) -> Option<(usize, FrameKind)> {
+        let (short, long) = if self.config.packet_marker < self.config.message_marker {
+            (FrameKind::Packet, FrameKind::Message)
+        } else {
+            (FrameKind::Message, FrameKind::Packet)
+        };
+        let short_len = self.config.marker_len(short);
+        let long_len = self.config.marker_len(long);
+
+        let mut detector = MarkerDetector::new(short_len);
+        let mut candidate = None;
+        for (i, &byte) in self.data.iter().enumerate().skip(from) {
+            if self.allowed[byte as usize] {
+                detector.push(byte);
+            } else {
+                detector.skip();
+            }
+            let run_start = i + 1 - detector.run_len();
+            match candidate {
+                Some(start) if start != run_start => return Some((start, short)),
+                _ if detector.run_len() == long_len => return Some((run_start, long)),
+                None if detector.run_len() == short_len => candidate = Some(run_start),
+                _ => {}
+            }
+        }
+        candidate.map(|start| (start, short))
+    }
+}
+
+impl<'a> Iterator for Decoder<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Frame<'a>> {
+        let (offset, kind) = self.next_marker?;
+        let payload_offset = offset + self.config.marker_len(kind);
+        self.next_marker = self.find_marker(payload_offset);
+        let payload_end = self.next_marker.map_or(self.data.len(), |(start, _)| start);
+        Some(Frame {
+            kind,
+            offset,
+            payload_offset,
+            payload: &self.data[payload_offset..payload_end],
+        })
+    }
+}
+
+/// ```
+/// use aoc::day6::{decode, FrameKind, ProtocolConfig};
+/// let config = ProtocolConfig{packet_marker: 2, message_marker: 3, alphabet: None};
+/// let frames: Vec<_> = decode(b"aaabcazzzxyqqq", config)
+///     .map(|frame| (frame.kind, frame.offset, frame.payload))
+///     .collect();
+/// assert_eq!(frames, vec![
+///     (FrameKind::Message, 2, &b""[..]),
+///     (FrameKind::Packet, 5, &b"z"[..]),
+///     (FrameKind::Message, 8, &b"qqq"[..]),
+/// ]);
+///
+/// let config = ProtocolConfig{packet_marker: 2, message_marker: 3, alphabet: Some(b"ab".to_vec())};
+/// let frames: Vec<_> = decode(b"aXbaab", config).map(|frame| (frame.kind, frame.offset)).collect();
+/// assert_eq!(frames, vec![(FrameKind::Packet, 2), (FrameKind::Packet, 4)]);
+/// ```
+pub fn decode(data: &[u8], config: ProtocolConfig) -> Decoder<'_> {
+    Decoder::new(data, config)
+}
 
 /// ```
 /// use aoc::day6::part1;
-/// assert_eq!(part1(Vec::from_iter("bvwbjplbgvbhsrlpgdmjqwftvncz".chars()), 4), 5);
-/// assert_eq!(part1(Vec::from_iter("nppdvjthqldpwncqszvftbrmjlhg".chars()), 4), 6);
-/// assert_eq!(part1(Vec::from_iter("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw".chars()), 4), 11);
-/// assert_eq!(part1(Vec::from_iter("mjqjpqmgbljsphdztnvjfqwrcgsmlb".chars()), 14), 19);
-/// ```
-pub fn part1(communication: Vec<char>, window_size: usize) -> usize {
-    let mut first_match =
-        communication
-            .windows(window_size)
-            .enumerate()
-            .filter(|(_idx, window)| {
-                let s: HashSet<&char> = HashSet::from_iter(*window);
-                s.len() == window_size
-            });
-    first_match.next().unwrap().0 + window_size
+/// assert_eq!(part1("bvwbjplbgvbhsrlpgdmjqwftvncz", 4), Some(5));
+/// assert_eq!(part1("nppdvjthqldpwncqszvftbrmjlhg", 4), Some(6));
+/// assert_eq!(part1("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4), Some(11));
+/// assert_eq!(part1("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14), Some(19));
+/// assert_eq!(part1("abab", 3), None);
+/// ```
+pub fn part1(communication: &str, window_size: usize) -> Option<usize> {
+    markers(communication.bytes(), window_size).next()
 }
 
 pub fn run() {
     let mut lines = read_lines("in/day6.in").unwrap();
-    let communication = lines
-        .next()
-        .unwrap()
-        .unwrap()
-        .chars()
-        .collect::<Vec<char>>();
-
-    println!("Day 6, part 1: {}", part1(communication.clone(), 4));
-    println!("Day 6, part 2: {}", part1(communication, 14));
+    let communication = lines.next().unwrap().unwrap();
+
+    println!("Day 6, part 1: {}", part1(&communication, 4).unwrap());
+    println!("Day 6, part 2: {}", part1(&communication, 14).unwrap());
 }
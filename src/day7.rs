@@ -1,19 +1,106 @@
 use crate::utils::read_lines;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum Tree {
     Directory(String, Vec<Tree>),
     File(String, u64),
 }
 
+impl Tree {
+    /// Walks a directory on disk. The top directory is named `root`, like the
+    /// one built from a transcript; entries are sorted by name and symlinks
+    /// are skipped.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Tree> {
+        Tree::from_path_impl("root".to_owned(), path.as_ref())
+    }
+
+    fn from_path_impl(name: String, path: &Path) -> io::Result<Tree> {
+        let mut children = vec![];
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let child_name = entry.file_name().to_string_lossy().into_owned();
+            let metadata = fs::symlink_metadata(entry.path())?;
+            if metadata.is_dir() {
+                children.push(Tree::from_path_impl(child_name, &entry.path())?);
+            } else if metadata.is_file() {
+                children.push(Tree::File(child_name, metadata.len()));
+            }
+        }
+        children.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(Tree::Directory(name, children))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Tree::Directory(name, _) | Tree::File(name, _) => name,
+        }
+    }
+
+    /// A `$ cd`/`$ ls` transcript in the puzzle format that `parse_transcript`
+    /// turns back into this tree. Directory names are quoted in `cd` where
+    /// needed; names containing line breaks cannot be represented.
+    /// ```
+    /// use aoc::day7::{parse_transcript, Tree};
+    /// use std::fs;
+    /// use std::time::{SystemTime, UNIX_EPOCH};
+    ///
+    /// let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    /// let dir = std::env::temp_dir().join(format!("aoc-day7-{}-{}", std::process::id(), nanos));
+    /// fs::create_dir_all(dir.join("a/my docs")).unwrap();
+    /// fs::write(dir.join("a/my docs/i"), vec![0; 584]).unwrap();
+    /// fs::write(dir.join("b.txt"), "hello").unwrap();
+    ///
+    /// let tree = Tree::from_path(&dir);
+    /// fs::remove_dir_all(&dir).unwrap();
+    /// let tree = tree.unwrap();
+    /// let transcript = tree.to_transcript();
+    /// assert_eq!(
+    ///     transcript,
+    ///     vec!["$ cd /", "$ ls", "dir a", "5 b.txt", "$ cd a", "$ ls", "dir my docs",
+    ///          "$ cd \"my docs\"", "$ ls", "584 i", "$ cd ..", "$ cd .."]
+    /// );
+    /// assert_eq!(parse_transcript(&transcript).unwrap().to_tree(), tree);
+    /// ```
+    pub fn to_transcript(&self) -> Vec<String> {
+        let mut lines = vec!["$ cd /".to_owned()];
+        self.to_transcript_impl(&mut lines);
+        lines
+    }
+
+    fn to_transcript_impl(&self, lines: &mut Vec<String>) {
+        let children = match self {
+            Tree::Directory(_, children) => children,
+            Tree::File(_, _) => return,
+        };
+        lines.push("$ ls".to_owned());
+        for child in children {
+            lines.push(match child {
+                Tree::Directory(name, _) => format!("dir {}", name),
+                Tree::File(name, size) => format!("{} {}", size, name),
+            });
+        }
+        for child in children {
+            if let Tree::Directory(name, _) = child {
+                lines.push(format!("$ cd {}", quote(name)));
+                child.to_transcript_impl(lines);
+                lines.push("$ cd ..".to_owned());
+            }
+        }
+    }
+}
+
 /// ```
 /// use aoc::day7::{subtree_sums, Tree};
 /// assert_eq!(
 ///   subtree_sums(
 ///     &Tree::Directory("root".to_owned(), vec![Tree::Directory("a".to_owned(), vec![]), Tree::Directory("b".to_owned(), vec![Tree::File("file".to_owned(), 32)]), ]),
 ///   ),
-///   vec![]
+///   vec![("/root/a".to_owned(), 0), ("/root/b".to_owned(), 32), ("/root".to_owned(), 32)]
 /// );
 /// ```
 pub fn subtree_sums(filesystem: &Tree) -> Vec<(String, u64)> {
@@ -36,86 +123,775 @@ pub fn subtree_sums_impl(filesystem: &Tree, sizes: &mut Vec<(String, u64)>, path
     }
 }
 
-pub fn build_tree(graph: &HashMap<String, Vec<(u64, String)>>) -> Tree {
-    build_tree_impl("root".to_owned(), graph)
+/// A file or directory found in a `Tree`, with its total size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Found {
+    /// Path in the `subtree_sums` format, e.g. `/root/a/e`.
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Every file and directory in pre-order, with sizes computed in one pass.
+pub fn entries(filesystem: &Tree) -> Vec<Found> {
+    let mut found = vec![];
+    entries_impl(filesystem, "", &mut found);
+    found
+}
+
+fn entries_impl(filesystem: &Tree, parent: &str, found: &mut Vec<Found>) -> u64 {
+    match filesystem {
+        Tree::Directory(name, children) => {
+            let path = format!("{}/{}", parent, name);
+            let idx = found.len();
+            found.push(Found {
+                path: path.clone(),
+                is_dir: true,
+                size: 0,
+            });
+            let size = children
+                .iter()
+                .map(|child| entries_impl(child, &path, found))
+                .sum();
+            found[idx].size = size;
+            size
+        }
+        Tree::File(name, size) => {
+            found.push(Found {
+                path: format!("{}/{}", parent, name),
+                is_dir: false,
+                size: *size,
+            });
+            *size
+        }
+    }
+}
+
+/// Indented listing in the style of the puzzle statement, with sizes.
+/// ```
+/// use aoc::day7::{tree_listing, Tree};
+/// let tree = Tree::Directory("root".to_owned(), vec![
+///     Tree::Directory("a".to_owned(), vec![Tree::File("i".to_owned(), 584)]),
+///     Tree::File("b.txt".to_owned(), 100),
+/// ]);
+/// assert_eq!(
+///     tree_listing(&tree),
+///     "- root (dir, size=684)\n  - a (dir, size=584)\n    - i (file, size=584)\n  - b.txt (file, size=100)\n"
+/// );
+/// ```
+pub fn tree_listing(filesystem: &Tree) -> String {
+    entries(filesystem)
+        .into_iter()
+        .map(|found| {
+            let depth = found.path.matches('/').count() - 1;
+            let name = found.path.rsplit('/').next().unwrap();
+            let kind = if found.is_dir { "dir" } else { "file" };
+            format!(
+                "{}- {} ({}, size={})\n",
+                "  ".repeat(depth),
+                name,
+                kind,
+                found.size
+            )
+        })
+        .collect()
+}
+
+/// Directory sizes, largest first, like `du | sort -rn`.
+/// ```
+/// use aoc::day7::{du, Tree};
+/// let tree = Tree::Directory("root".to_owned(), vec![
+///     Tree::Directory("a".to_owned(), vec![Tree::File("i".to_owned(), 5)]),
+///     Tree::Directory("b".to_owned(), vec![]),
+/// ]);
+/// assert_eq!(
+///     du(&tree),
+///     vec![("/root".to_owned(), 5), ("/root/a".to_owned(), 5), ("/root/b".to_owned(), 0)]
+/// );
+/// ```
+pub fn du(filesystem: &Tree) -> Vec<(String, u64)> {
+    let mut sizes: Vec<(String, u64)> = entries(filesystem)
+        .into_iter()
+        .filter(|found| found.is_dir)
+        .map(|found| (found.path, found.size))
+        .collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sizes
+}
+
+/// Shell-style glob supporting `*` and `?`.
+/// ```
+/// use aoc::day7::glob_match;
+/// assert!(glob_match("*.txt", "b.txt"));
+/// assert!(glob_match("?.d*", "c.dat"));
+/// assert!(!glob_match("*.txt", "b.txt.bak"));
+/// ```
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it currently matches up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
-pub fn build_tree_impl(node: String, graph: &HashMap<String, Vec<(u64, String)>>) -> Tree {
-    let nodes = &graph[&node];
-    return Tree::Directory(
-        node.clone(),
-        nodes
+/// Entries whose name matches `pattern` and which satisfy `predicate`, like
+/// `find -name pattern`.
+/// ```
+/// use aoc::day7::{find, Tree};
+/// let tree = Tree::Directory("root".to_owned(), vec![
+///     Tree::File("b.txt".to_owned(), 14848514),
+///     Tree::File("c.dat".to_owned(), 8504156),
+///     Tree::File("small.txt".to_owned(), 10),
+/// ]);
+/// let big_text: Vec<String> = find(&tree, "*.txt", |found| found.size > 1000)
+///     .into_iter()
+///     .map(|found| found.path)
+///     .collect();
+/// assert_eq!(big_text, vec!["/root/b.txt"]);
+/// ```
+pub fn find<P: Fn(&Found) -> bool>(filesystem: &Tree, pattern: &str, predicate: P) -> Vec<Found> {
+    entries(filesystem)
+        .into_iter()
+        .filter(|found| glob_match(pattern, found.path.rsplit('/').next().unwrap()))
+        .filter(|found| predicate(found))
+        .collect()
+}
+
+/// Smallest directory whose deletion leaves at least `required_space` free on
+/// a disk of `disk_size`, given the directory sizes from `du`. The largest
+/// size is taken as the space in use. `None` if no directory is big enough.
+/// ```
+/// use aoc::day7::{du, smallest_deletion, Tree};
+/// let tree = Tree::Directory("root".to_owned(), vec![
+///     Tree::Directory("a".to_owned(), vec![Tree::File("f".to_owned(), 30)]),
+///     Tree::Directory("b".to_owned(), vec![Tree::File("g".to_owned(), 50)]),
+/// ]);
+/// let sizes = du(&tree);
+/// assert_eq!(smallest_deletion(&sizes, 100, 55), Some(("/root/b", 50)));
+/// assert_eq!(smallest_deletion(&sizes, 100, 20), Some(("/root/a", 30)));
+/// assert_eq!(smallest_deletion(&sizes, 100, 200), None);
+/// ```
+pub fn smallest_deletion(
+    sizes: &[(String, u64)],
+    disk_size: u64,
+    required_space: u64,
+) -> Option<(&str, u64)> {
+    let used = sizes.iter().map(|(_, size)| *size).max()?;
+    let to_delete = required_space.saturating_sub(disk_size.saturating_sub(used));
+    sizes
+        .iter()
+        .filter(|(_, size)| *size >= to_delete)
+        .min_by_key(|(_, size)| *size)
+        .map(|(path, size)| (path.as_str(), *size))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShellError {
+    UnknownCommand(String),
+    /// A line that is neither a command nor part of an `ls` listing.
+    UnexpectedOutput(String),
+    NoSuchFileOrDirectory(String),
+    NotADirectory(String),
+    /// `rm` without `-r` on a directory.
+    IsADirectory(String),
+    AlreadyExists(String),
+    /// Moving a directory into itself, or moving or removing the root or the
+    /// working directory.
+    InvalidTarget(String),
+    /// An `ls` listing that disagrees with what is already known.
+    InconsistentListing(String),
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShellError::UnknownCommand(line) => write!(f, "unknown command: {}", line),
+            ShellError::UnexpectedOutput(line) => write!(f, "unexpected output: {}", line),
+            ShellError::NoSuchFileOrDirectory(path) => {
+                write!(f, "{}: no such file or directory", path)
+            }
+            ShellError::NotADirectory(path) => write!(f, "{}: not a directory", path),
+            ShellError::IsADirectory(path) => write!(f, "{}: is a directory", path),
+            ShellError::AlreadyExists(path) => write!(f, "{}: already exists", path),
+            ShellError::InvalidTarget(path) => write!(f, "{}: invalid target", path),
+            ShellError::InconsistentListing(path) => {
+                write!(f, "{}: listing does not match earlier state", path)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Directory {
+    entries: BTreeMap<String, Entry>,
+    /// Whether an `ls` of this directory has been seen, so its entries are complete.
+    listed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    Directory(Directory),
+    File(u64),
+}
+
+/// One line of `ls` output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Listing {
+    Directory(String),
+    File(String, u64),
+}
+
+/// In-memory filesystem with a working directory. Paths are `/`-separated,
+/// absolute when they start with `/`, and may contain `.` and `..`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filesystem {
+    root: Directory,
+    cwd: Vec<String>,
+}
+
+impl Filesystem {
+    pub fn new() -> Filesystem {
+        Filesystem::default()
+    }
+
+    /// Absolute path of the working directory.
+    pub fn cwd(&self) -> String {
+        format!("/{}", self.cwd.join("/"))
+    }
+
+    /// Normalised components of `path`, resolved against the working directory.
+    /// ```
+    /// use aoc::day7::Filesystem;
+    /// let mut fs = Filesystem::new();
+    /// fs.mkdir("/a").unwrap();
+    /// fs.cd("a").unwrap();
+    /// assert_eq!(fs.resolve("b/../c/./d"), vec!["a", "c", "d"]);
+    /// assert_eq!(fs.resolve("/../x"), vec!["x"]);
+    /// ```
+    pub fn resolve(&self, path: &str) -> Vec<String> {
+        let mut components = if path.starts_with('/') {
+            vec![]
+        } else {
+            self.cwd.clone()
+        };
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    components.pop();
+                }
+                name => components.push(name.to_owned()),
+            }
+        }
+        components
+    }
+
+    fn directory(&self, components: &[String], path: &str) -> Result<&Directory, ShellError> {
+        let mut dir = &self.root;
+        for name in components {
+            dir = match dir.entries.get(name) {
+                Some(Entry::Directory(child)) => child,
+                Some(Entry::File(_)) => return Err(ShellError::NotADirectory(path.to_owned())),
+                None => return Err(ShellError::NoSuchFileOrDirectory(path.to_owned())),
+            };
+        }
+        Ok(dir)
+    }
+
+    fn directory_mut(
+        &mut self,
+        components: &[String],
+        path: &str,
+    ) -> Result<&mut Directory, ShellError> {
+        let mut dir = &mut self.root;
+        for name in components {
+            dir = match dir.entries.get_mut(name) {
+                Some(Entry::Directory(child)) => child,
+                Some(Entry::File(_)) => return Err(ShellError::NotADirectory(path.to_owned())),
+                None => return Err(ShellError::NoSuchFileOrDirectory(path.to_owned())),
+            };
+        }
+        Ok(dir)
+    }
+
+    /// Parent directory and entry name of a path, which must not be the root.
+    fn parent_mut(&mut self, path: &str) -> Result<(&mut Directory, String), ShellError> {
+        let mut components = self.resolve(path);
+        let name = components
+            .pop()
+            .ok_or_else(|| ShellError::InvalidTarget(path.to_owned()))?;
+        Ok((self.directory_mut(&components, path)?, name))
+    }
+
+    pub fn cd(&mut self, path: &str) -> Result<(), ShellError> {
+        let components = self.resolve(path);
+        self.directory(&components, path)?;
+        self.cwd = components;
+        Ok(())
+    }
+
+    /// Entries of a directory, sorted by name.
+    pub fn ls(&self, path: &str) -> Result<Vec<Listing>, ShellError> {
+        let dir = self.directory(&self.resolve(path), path)?;
+        Ok(dir
+            .entries
             .iter()
-            .map(|child| {
-                if child.0 != 0 {
-                    Tree::File(child.1.clone(), child.0)
-                } else {
-                    build_tree_impl(node.clone() + "/" + &child.1, graph)
+            .map(|(name, entry)| match entry {
+                Entry::Directory(_) => Listing::Directory(name.clone()),
+                Entry::File(size) => Listing::File(name.clone(), *size),
+            })
+            .collect())
+    }
+
+    pub fn mkdir(&mut self, path: &str) -> Result<(), ShellError> {
+        let (parent, name) = self.parent_mut(path)?;
+        if parent.entries.contains_key(&name) {
+            return Err(ShellError::AlreadyExists(path.to_owned()));
+        }
+        parent
+            .entries
+            .insert(name, Entry::Directory(Directory::default()));
+        Ok(())
+    }
+
+    /// Creates a file of the given size, or resizes an existing one.
+    pub fn touch(&mut self, path: &str, size: u64) -> Result<(), ShellError> {
+        let (parent, name) = self.parent_mut(path)?;
+        match parent.entries.get_mut(&name) {
+            Some(Entry::Directory(_)) => Err(ShellError::IsADirectory(path.to_owned())),
+            Some(Entry::File(file_size)) => {
+                *file_size = size;
+                Ok(())
+            }
+            None => {
+                parent.entries.insert(name, Entry::File(size));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn rm(&mut self, path: &str, recursive: bool) -> Result<(), ShellError> {
+        let target = self.resolve(path);
+        if self.cwd.starts_with(&target) {
+            return Err(ShellError::InvalidTarget(path.to_owned()));
+        }
+        let (parent, name) = self.parent_mut(path)?;
+        match parent.entries.get(&name) {
+            None => Err(ShellError::NoSuchFileOrDirectory(path.to_owned())),
+            Some(Entry::Directory(_)) if !recursive => {
+                Err(ShellError::IsADirectory(path.to_owned()))
+            }
+            Some(_) => {
+                parent.entries.remove(&name);
+                Ok(())
+            }
+        }
+    }
+
+    /// Moves `source` into `destination` if that is an existing directory,
+    /// otherwise renames it to `destination`.
+    /// ```
+    /// use aoc::day7::{Filesystem, ShellError};
+    /// let mut fs = Filesystem::new();
+    /// fs.mkdir("/a").unwrap();
+    /// fs.mkdir("/b").unwrap();
+    /// fs.touch("/a/f", 10).unwrap();
+    /// fs.mv("/a", "/b").unwrap();
+    /// fs.mv("/b/a/f", "/g").unwrap();
+    /// assert!(fs.ls("/b/a").unwrap().is_empty());
+    /// assert_eq!(fs.mv("/b", "/b/a"), Err(ShellError::InvalidTarget("/b".to_owned())));
+    /// ```
+    pub fn mv(&mut self, source: &str, destination: &str) -> Result<(), ShellError> {
+        let from = self.resolve(source);
+        let mut to = self.resolve(destination);
+        if let Ok(dir) = self.directory(&to, destination) {
+            let name = from
+                .last()
+                .ok_or_else(|| ShellError::InvalidTarget(source.to_owned()))?;
+            if dir.entries.contains_key(name) {
+                return Err(ShellError::AlreadyExists(destination.to_owned()));
+            }
+            to.push(name.clone());
+        } else {
+            let (name, parent) = to
+                .split_last()
+                .ok_or_else(|| ShellError::InvalidTarget(source.to_owned()))?;
+            if self
+                .directory(parent, destination)?
+                .entries
+                .contains_key(name)
+            {
+                return Err(ShellError::AlreadyExists(destination.to_owned()));
+            }
+        }
+        if from.is_empty() || to.starts_with(&from) || self.cwd.starts_with(&from) {
+            return Err(ShellError::InvalidTarget(source.to_owned()));
+        }
+
+        let (from_name, from_parent) = from.split_last().unwrap();
+        let entry = self
+            .directory_mut(from_parent, source)?
+            .entries
+            .remove(from_name)
+            .ok_or_else(|| ShellError::NoSuchFileOrDirectory(source.to_owned()))?;
+        let (to_name, to_parent) = to.split_last().unwrap();
+        self.directory_mut(to_parent, destination)?
+            .entries
+            .insert(to_name.clone(), entry);
+        Ok(())
+    }
+
+    /// Records `ls` output for a directory. The first listing of a directory
+    /// adds its entries; later listings must match the current state.
+    pub fn record_listing(&mut self, path: &str, listing: &[Listing]) -> Result<(), ShellError> {
+        let components = self.resolve(path);
+        let dir = self.directory_mut(&components, path)?;
+        let inconsistent = || ShellError::InconsistentListing(path.to_owned());
+
+        let mut seen: BTreeMap<&str, &Listing> = BTreeMap::new();
+        for entry in listing {
+            let name = match entry {
+                Listing::Directory(name) | Listing::File(name, _) => name,
+            };
+            if seen.insert(name, entry).is_some() {
+                return Err(inconsistent());
+            }
+        }
+        if dir
+            .entries
+            .keys()
+            .any(|name| !seen.contains_key(name.as_str()))
+        {
+            return Err(inconsistent());
+        }
+        if dir.listed && seen.len() != dir.entries.len() {
+            return Err(inconsistent());
+        }
+        for (name, entry) in seen {
+            match (dir.entries.get(name), entry) {
+                (None, Listing::Directory(_)) => {
+                    dir.entries
+                        .insert(name.to_owned(), Entry::Directory(Directory::default()));
                 }
+                (None, Listing::File(_, size)) => {
+                    dir.entries.insert(name.to_owned(), Entry::File(*size));
+                }
+                (Some(Entry::Directory(_)), Listing::Directory(_)) => {}
+                (Some(Entry::File(size)), Listing::File(_, listed_size)) if size == listed_size => {
+                }
+                _ => return Err(inconsistent()),
+            }
+        }
+        dir.listed = true;
+        Ok(())
+    }
+
+    /// The whole filesystem as a `Tree` whose root directory is named `root`.
+    pub fn to_tree(&self) -> Tree {
+        directory_to_tree("root".to_owned(), &self.root)
+    }
+}
+
+fn directory_to_tree(name: String, dir: &Directory) -> Tree {
+    Tree::Directory(
+        name,
+        dir.entries
+            .iter()
+            .map(|(name, entry)| match entry {
+                Entry::Directory(child) => directory_to_tree(name.clone(), child),
+                Entry::File(size) => Tree::File(name.clone(), *size),
             })
             .collect(),
-    );
+    )
 }
 
-pub fn run() {
-    let lines = read_lines("in/day7.in").unwrap();
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Cd(String),
+    /// `ls` of the working directory, or of the given path.
+    Ls(Option<String>),
+    Mkdir(String),
+    /// `touch <path> [size]`, size defaulting to 0.
+    Touch(String, u64),
+    Rm {
+        path: String,
+        recursive: bool,
+    },
+    Mv(String, String),
+}
 
-    let mut paths: HashMap<String, Vec<(u64, String)>> = HashMap::new();
-
-    let mut cur_path = vec![];
-    let mut cur_ls = vec![];
-    for l in lines {
-        let line = l.unwrap();
-        scan!(&line;
-            ("$ cd ", ..cddir) => {
-                if !cur_ls.is_empty() {
-                    paths.insert(cur_path.join("/"), cur_ls);
-                    cur_ls = vec![];
+/// Splits a command line into words like a shell: words are separated by
+/// whitespace, `"..."` and `'...'` quote, and `\` escapes the next character
+/// (also inside double quotes). `None` on an unterminated quote or escape.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' => word.get_or_insert_with(String::new).push(chars.next()?),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
                 }
-                if cddir == ".." {
-                    cur_path.pop();
-                } else if cddir == "/" {
-                    cur_path = vec!["root".to_owned()];
-                } else {
-                    cur_path.push(cddir.to_string());
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => word.push(chars.next()?),
+                        c => word.push(c),
+                    }
                 }
-            },
-            ("$ ls") => {
-            },
-            ("dir ", ..dir_name) => {
-                cur_ls.push((0, dir_name.to_owned()));
-            },
-            (let size: u64, ..file_name) => {
-                cur_ls.push((size, file_name.to_owned()));
-            },
-        )
-        .unwrap();
-    }
-    if !cur_ls.is_empty() {
-        paths.insert(cur_path.join("/"), cur_ls);
-    }
-
-    let tree = build_tree(&paths);
-    let sub_sums: u64 = subtree_sums(&tree)
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Quotes a name so that `parse_command` reads it back as one word.
+fn quote(name: &str) -> String {
+    let special = |c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\');
+    if !name.is_empty() && !name.contains(special) {
+        return name.to_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in name.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parses a command line without its leading `$ `. Operands of every command
+/// are split the same way, so names containing spaces must be quoted or
+/// escaped.
+/// ```
+/// use aoc::day7::{parse_command, Command};
+/// assert_eq!(parse_command("cd .."), Ok(Command::Cd("..".to_owned())));
+/// assert_eq!(parse_command("cd \"My Documents\""), Ok(Command::Cd("My Documents".to_owned())));
+/// assert_eq!(parse_command("touch a/b 12"), Ok(Command::Touch("a/b".to_owned(), 12)));
+/// assert_eq!(
+///     parse_command("mv 'a b' c\\ d"),
+///     Ok(Command::Mv("a b".to_owned(), "c d".to_owned()))
+/// );
+/// assert_eq!(
+///     parse_command("rm -r x"),
+///     Ok(Command::Rm{path: "x".to_owned(), recursive: true})
+/// );
+/// assert!(parse_command("cd My Documents").is_err());
+/// assert!(parse_command("mkdir \"x").is_err());
+/// assert!(parse_command("cat x").is_err());
+/// ```
+pub fn parse_command(line: &str) -> Result<Command, ShellError> {
+    let unknown = || ShellError::UnknownCommand(line.to_owned());
+    let words = split_words(line).ok_or_else(unknown)?;
+    let args: Vec<&str> = words.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["cd", path] => Ok(Command::Cd(path.to_string())),
+        ["ls"] => Ok(Command::Ls(None)),
+        ["ls", path] => Ok(Command::Ls(Some(path.to_string()))),
+        ["mkdir", path] => Ok(Command::Mkdir(path.to_string())),
+        ["touch", path] => Ok(Command::Touch(path.to_string(), 0)),
+        ["touch", path, size] => Ok(Command::Touch(
+            path.to_string(),
+            size.parse().map_err(|_| unknown())?,
+        )),
+        ["rm", path] => Ok(Command::Rm {
+            path: path.to_string(),
+            recursive: false,
+        }),
+        ["rm", "-r", path] => Ok(Command::Rm {
+            path: path.to_string(),
+            recursive: true,
+        }),
+        ["mv", source, destination] => Ok(Command::Mv(source.to_string(), destination.to_string())),
+        _ => Err(unknown()),
+    }
+}
+
+/// Parses one line of `ls` output.
+/// ```
+/// use aoc::day7::{parse_listing, Listing};
+/// assert_eq!(parse_listing("dir a"), Ok(Listing::Directory("a".to_owned())));
+/// assert_eq!(parse_listing("584 i"), Ok(Listing::File("i".to_owned(), 584)));
+/// ```
+pub fn parse_listing(line: &str) -> Result<Listing, ShellError> {
+    match line.split_once(' ') {
+        Some(("dir", name)) if !name.is_empty() => Ok(Listing::Directory(name.to_owned())),
+        Some((size, name)) if !name.is_empty() => size
+            .parse()
+            .map(|size| Listing::File(name.to_owned(), size))
+            .map_err(|_| ShellError::UnexpectedOutput(line.to_owned())),
+        _ => Err(ShellError::UnexpectedOutput(line.to_owned())),
+    }
+}
+
+/// Replays a terminal transcript line by line against a `Filesystem`.
+#[derive(Default)]
+pub struct Shell {
+    pub filesystem: Filesystem,
+    /// Number of lines fed so far.
+    lines_fed: usize,
+    /// Line of the pending `ls`, the directory being listed and the output
+    /// collected so far.
+    listing: Option<(usize, String, Vec<Listing>)>,
+}
+
+impl Shell {
+    pub fn new() -> Shell {
+        Shell::default()
+    }
+
+    pub fn execute(&mut self, command: Command) -> Result<(), ShellError> {
+        self.flush_listing()?;
+        let fs = &mut self.filesystem;
+        match command {
+            Command::Cd(path) => fs.cd(&path),
+            Command::Ls(path) => {
+                let path = path.unwrap_or_else(|| fs.cwd());
+                let absolute = format!("/{}", fs.resolve(&path).join("/"));
+                fs.ls(&absolute)?;
+                self.listing = Some((self.lines_fed, absolute, vec![]));
+                Ok(())
+            }
+            Command::Mkdir(path) => fs.mkdir(&path),
+            Command::Touch(path, size) => fs.touch(&path, size),
+            Command::Rm { path, recursive } => fs.rm(&path, recursive),
+            Command::Mv(source, destination) => fs.mv(&source, &destination),
+        }
+    }
+
+    /// Feeds one transcript line: a `$ ` command or `ls` output.
+    pub fn feed(&mut self, line: &str) -> Result<(), ShellError> {
+        self.lines_fed += 1;
+        if let Some(command) = line.strip_prefix("$ ") {
+            return self.execute(parse_command(command)?);
+        }
+        match &mut self.listing {
+            Some((_, _, output)) => {
+                output.push(parse_listing(line)?);
+                Ok(())
+            }
+            None => Err(ShellError::UnexpectedOutput(line.to_owned())),
+        }
+    }
+
+    /// 1-based line of the `ls` whose output is still pending, counting the
+    /// lines given to `feed`.
+    pub fn listing_line(&self) -> Option<usize> {
+        self.listing.as_ref().map(|(line, _, _)| *line)
+    }
+
+    /// Applies any pending `ls` output.
+    pub fn flush_listing(&mut self) -> Result<(), ShellError> {
+        match self.listing.take() {
+            Some((_, path, output)) => self.filesystem.record_listing(&path, &output),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TranscriptError {
+    /// 1-based line number in the transcript.
+    pub line: usize,
+    pub error: ShellError,
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+/// ```
+/// use aoc::day7::{parse_transcript, subtree_sums, ShellError, TranscriptError};
+/// let transcript = ["$ cd /", "$ ls", "dir a", "10 b", "$ cd a", "$ ls", "5 c", "$ mv c ../d"];
+/// let fs = parse_transcript(transcript).unwrap();
+/// assert_eq!(
+///     subtree_sums(&fs.to_tree()),
+///     vec![("/root/a".to_owned(), 0), ("/root".to_owned(), 15)]
+/// );
+///
+/// let conflicting = ["$ ls", "dir a", "$ ls", "1 a"];
+/// assert_eq!(
+///     parse_transcript(conflicting),
+///     Err(TranscriptError{line: 3, error: ShellError::InconsistentListing("/".to_owned())})
+/// );
+/// let conflicting = ["$ cd /", "$ ls", "dir a", "$ ls", "1 a", "$ cd a"];
+/// assert_eq!(
+///     parse_transcript(conflicting),
+///     Err(TranscriptError{line: 4, error: ShellError::InconsistentListing("/".to_owned())})
+/// );
+/// ```
+pub fn parse_transcript<I, S>(lines: I) -> Result<Filesystem, TranscriptError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    // Listing errors are reported at the `ls` that produced the output.
+    fn flush(shell: &mut Shell) -> Result<(), TranscriptError> {
+        let line = shell.listing_line().unwrap_or_default();
+        shell
+            .flush_listing()
+            .map_err(|error| TranscriptError { line, error })
+    }
+
+    let mut shell = Shell::new();
+    for (line_number, line) in (1..).zip(lines) {
+        let line = line.as_ref();
+        if line.starts_with("$ ") {
+            flush(&mut shell)?;
+        }
+        shell.feed(line).map_err(|error| TranscriptError {
+            line: line_number,
+            error,
+        })?;
+    }
+    flush(&mut shell)?;
+    Ok(shell.filesystem)
+}
+
+pub fn run() {
+    let lines = read_lines("in/day7.in").unwrap();
+    let filesystem = parse_transcript(lines.map(|l| l.unwrap())).unwrap();
+
+    let sizes = du(&filesystem.to_tree());
+    let sub_sums: u64 = sizes
         .iter()
         .map(|elem| elem.1)
         .filter(|elem| elem <= &100000)
         .sum();
     println!("Day 7, part 1: {}", sub_sums);
 
-    let current_used_space = subtree_sums(&tree)
-        .into_iter()
-        .map(|elem| elem.1)
-        .max()
-        .unwrap();
-
-    let to_delete = 30000000 - (70000000 - current_used_space);
-
-    let dir_size_to_delete = subtree_sums(&tree)
-        .into_iter()
-        .map(|elem| elem.1)
-        .filter(|elem| elem >= &to_delete)
-        .min()
-        .unwrap();
+    let (_, dir_size_to_delete) = smallest_deletion(&sizes, 70000000, 30000000).unwrap();
     println!("Day 7, part 2: {:?}", &dir_size_to_delete);
 }
@@ -1,119 +1,278 @@
 use crate::utils::read_lines;
 
-pub fn next(p: (i32, i32), dp: (i32, i32)) -> (i32, i32) {
-    (p.0 + dp.0, p.1 + dp.1)
+/// The four directions a tree can be seen from or look towards, as
+/// `(row, column)` steps.
+pub const AXES: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+pub const DIAGONALS: [(i32, i32); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+pub const KNIGHT_MOVES: [(i32, i32); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+/// Which trees block the view of a tree of a given height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blocking {
+    /// Trees at least as tall as the viewer block it, as in the puzzle.
+    AtLeastAsTall,
+    /// Only strictly taller trees block the viewer.
+    Taller,
 }
 
-pub fn is_out_of_grid(p: (i32, i32), dimensions: (usize, usize)) -> bool {
-    p.0 < 0 || p.0 >= dimensions.0 as i32 || p.1 < 0 || p.1 >= dimensions.1 as i32
+impl Blocking {
+    pub fn blocks(self, height: u32, viewer: u32) -> bool {
+        match self {
+            Blocking::AtLeastAsTall => height >= viewer,
+            Blocking::Taller => height > viewer,
+        }
+    }
+}
+
+/// Directions a tree looks along, one grid step at a time, and what blocks it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SightRules {
+    pub directions: Vec<(i32, i32)>,
+    pub blocking: Blocking,
+}
+
+impl SightRules {
+    /// ```
+    /// use aoc::day8::{Blocking, SightRules, KNIGHT_MOVES};
+    /// let rules = SightRules::new(KNIGHT_MOVES.to_vec(), Blocking::Taller);
+    /// assert_eq!(rules.directions.len(), 8);
+    /// ```
+    ///
+    /// A zero step would never leave the grid, so it is rejected:
+    /// ```should_panic
+    /// use aoc::day8::{Blocking, SightRules};
+    /// SightRules::new(vec![(1, 0), (0, 0)], Blocking::Taller);
+    /// ```
+    pub fn new(directions: Vec<(i32, i32)>, blocking: Blocking) -> SightRules {
+        let rules = SightRules {
+            directions,
+            blocking,
+        };
+        rules.assert_valid();
+        rules
+    }
+
+    /// Also checked by every function taking rules, as the fields are public.
+    fn assert_valid(&self) {
+        assert!(
+            !self.directions.contains(&(0, 0)),
+            "Sight directions must not contain a zero step"
+        );
+    }
 }
 
-pub fn bfs(
-    start: (i32, i32),
-    dimensions: (usize, usize),
-    direction: (i32, i32),
-    grid: &[Vec<u32>],
-    visibility: &mut [Vec<bool>],
-) {
-    let mut tree_loc = start;
-    visibility[tree_loc.0 as usize][tree_loc.1 as usize] = true;
-    let mut prev_max = grid[tree_loc.0 as usize][tree_loc.1 as usize];
-    loop {
-        tree_loc = next(tree_loc, direction);
-        if is_out_of_grid(tree_loc, dimensions) {
-            return;
+impl Default for SightRules {
+    fn default() -> SightRules {
+        SightRules {
+            directions: AXES.to_vec(),
+            blocking: Blocking::AtLeastAsTall,
         }
-        let cur_tree = grid[tree_loc.0 as usize][tree_loc.1 as usize];
-        if prev_max < cur_tree {
-            visibility[tree_loc.0 as usize][tree_loc.1 as usize] = true;
-            prev_max = cur_tree
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Forest {
+    /// Whether each tree is visible from outside the grid.
+    pub visible: Vec<Vec<bool>>,
+    pub scenic_scores: Vec<Vec<u64>>,
+}
+
+/// Visibility and scenic score of every tree, in O(rows × columns).
+/// ```
+/// use aoc::day8::analyse;
+/// let grid = vec![
+///     vec![3, 0, 3, 7, 3],
+///     vec![2, 5, 5, 1, 2],
+///     vec![6, 5, 3, 3, 2],
+///     vec![3, 3, 5, 4, 9],
+///     vec![3, 5, 3, 9, 0],
+/// ];
+/// let forest = analyse(&grid);
+/// assert_eq!(forest.visible.iter().flatten().filter(|v| **v).count(), 21);
+/// assert!(!forest.visible[1][3]);
+/// assert_eq!(forest.scenic_scores[1][2], 4);
+/// assert_eq!(forest.scenic_scores[3][2], 8);
+/// assert_eq!(forest.scenic_scores[0][0], 0);
+/// ```
+pub fn analyse(grid: &[Vec<u32>]) -> Forest {
+    analyse_with(grid, &SightRules::default())
+}
+
+/// `analyse` along arbitrary directions with a configurable blocking rule.
+/// ```
+/// use aoc::day8::{analyse_with, Blocking, SightRules, DIAGONALS};
+/// let grid = vec![
+///     vec![1, 1, 1],
+///     vec![1, 1, 1],
+///     vec![1, 1, 1],
+/// ];
+/// let strict = analyse_with(&grid, &SightRules{directions: DIAGONALS.to_vec(), blocking: Blocking::AtLeastAsTall});
+/// assert!(!strict.visible[1][1]);
+/// assert!(strict.visible[0][1]);
+/// assert_eq!(strict.scenic_scores[1][1], 1);
+///
+/// let lenient = analyse_with(&grid, &SightRules{directions: DIAGONALS.to_vec(), blocking: Blocking::Taller});
+/// assert!(lenient.visible[1][1]);
+/// ```
+pub fn analyse_with(grid: &[Vec<u32>], rules: &SightRules) -> Forest {
+    rules.assert_valid();
+    let rows = grid.len();
+    let cols = grid.first().map_or(0, |row| row.len());
+    assert!(
+        grid.iter().all(|row| row.len() == cols),
+        "Grid must be rectangular"
+    );
+
+    let mut forest = Forest {
+        visible: vec![vec![false; cols]; rows],
+        scenic_scores: vec![vec![1; cols]; rows],
+    };
+    for &direction in &rules.directions {
+        sweep(grid, direction, rules.blocking, &mut forest);
+    }
+    forest
+}
+
+/// Looks from every tree towards `direction`. Each line of trees is walked
+/// from the edge it faces while a stack keeps the trees that still block the
+/// view, tallest at the bottom, so every tree is pushed and popped once.
+fn sweep(grid: &[Vec<u32>], direction: (i32, i32), blocking: Blocking, forest: &mut Forest) {
+    let rows = grid.len() as i32;
+    let cols = grid.first().map_or(0, |row| row.len()) as i32;
+    let inside = |(r, c): (i32, i32)| 0 <= r && r < rows && 0 <= c && c < cols;
+
+    // (position along the line, height) of trees that block the view.
+    let mut stack: Vec<(u64, u32)> = vec![];
+    for row in 0..rows {
+        for col in 0..cols {
+            if inside((row + direction.0, col + direction.1)) {
+                continue;
+            }
+            stack.clear();
+            let mut cell = (row, col);
+            let mut position = 0;
+            while inside(cell) {
+                let (r, c) = (cell.0 as usize, cell.1 as usize);
+                let height = grid[r][c];
+                while stack
+                    .last()
+                    .is_some_and(|&(_, top)| !blocking.blocks(top, height))
+                {
+                    stack.pop();
+                }
+                match stack.last() {
+                    Some(&(blocker, _)) => forest.scenic_scores[r][c] *= position - blocker,
+                    None => {
+                        forest.visible[r][c] = true;
+                        forest.scenic_scores[r][c] *= position;
+                    }
+                }
+                stack.push((position, height));
+                cell = (cell.0 - direction.0, cell.1 - direction.1);
+                position += 1;
+            }
         }
     }
 }
 
-pub fn bfs_2(start: (i32, i32), dimensions: (usize, usize), grid: &[Vec<u32>]) -> i32 {
-    let mut total_score = 1;
-    for direction in [(0, 1), (1, 0), (-1, 0), (0, -1)] {
-        let mut score = 0;
-        let mut tree_loc = start;
-        let start_tree_height = grid[tree_loc.0 as usize][tree_loc.1 as usize];
-        loop {
-            tree_loc = next(tree_loc, direction);
-            if is_out_of_grid(tree_loc, dimensions) {
+fn height_at(grid: &[Vec<u32>], (row, col): (i32, i32)) -> Option<u32> {
+    if row < 0 || col < 0 {
+        return None;
+    }
+    grid.get(row as usize)?.get(col as usize).cloned()
+}
+
+/// Trees that `tree` sees: in each direction, every tree up to and including
+/// the first one that blocks it.
+/// ```
+/// use aoc::day8::{sees, SightRules};
+/// let grid = vec![
+///     vec![3, 0, 3, 7, 3],
+///     vec![2, 5, 5, 1, 2],
+///     vec![6, 5, 3, 3, 2],
+///     vec![3, 3, 5, 4, 9],
+///     vec![3, 5, 3, 9, 0],
+/// ];
+/// assert_eq!(
+///     sees(&grid, &SightRules::default(), (1, 2)),
+///     vec![(0, 2), (2, 2), (3, 2), (1, 1), (1, 3), (1, 4)]
+/// );
+/// ```
+pub fn sees(grid: &[Vec<u32>], rules: &SightRules, tree: (usize, usize)) -> Vec<(usize, usize)> {
+    rules.assert_valid();
+    let viewer = grid[tree.0][tree.1];
+    let mut seen = vec![];
+    for &(dr, dc) in &rules.directions {
+        let mut cell = (tree.0 as i32 + dr, tree.1 as i32 + dc);
+        while let Some(height) = height_at(grid, cell) {
+            seen.push((cell.0 as usize, cell.1 as usize));
+            if rules.blocking.blocks(height, viewer) {
                 break;
             }
-            let cur_tree = grid[tree_loc.0 as usize][tree_loc.1 as usize];
-            if cur_tree < start_tree_height {
-                score += 1;
-            } else {
-                score += 1;
-                break;
+            cell = (cell.0 + dr, cell.1 + dc);
+        }
+    }
+    seen
+}
+
+/// Trees that see `tree`, i.e. have it in their `sees` list.
+/// ```
+/// use aoc::day8::{seen_by, SightRules};
+/// let grid = vec![
+///     vec![3, 0, 3, 7, 3],
+///     vec![2, 5, 5, 1, 2],
+///     vec![6, 5, 3, 3, 2],
+///     vec![3, 3, 5, 4, 9],
+///     vec![3, 5, 3, 9, 0],
+/// ];
+/// assert_eq!(
+///     seen_by(&grid, &SightRules::default(), (0, 1)),
+///     vec![(1, 1), (0, 2), (0, 3), (0, 0)]
+/// );
+/// ```
+pub fn seen_by(grid: &[Vec<u32>], rules: &SightRules, tree: (usize, usize)) -> Vec<(usize, usize)> {
+    rules.assert_valid();
+    let mut viewers = vec![];
+    for &(dr, dc) in &rules.directions {
+        // Walk away from `tree` against the viewing direction; a tree there
+        // sees `tree` unless something in between blocks it.
+        let mut cell = (tree.0 as i32 - dr, tree.1 as i32 - dc);
+        let mut tallest_between: Option<u32> = None;
+        while let Some(height) = height_at(grid, cell) {
+            if tallest_between.is_none_or(|between| !rules.blocking.blocks(between, height)) {
+                viewers.push((cell.0 as usize, cell.1 as usize));
             }
+            tallest_between = Some(tallest_between.map_or(height, |between| between.max(height)));
+            cell = (cell.0 - dr, cell.1 - dc);
         }
-        total_score *= score;
     }
-    total_score
+    viewers
 }
 
 pub fn run() {
     let lines = read_lines("in/day8.in").unwrap();
     let mut grid: Vec<Vec<u32>> = vec![];
-    let mut visibility: Vec<Vec<bool>> = vec![];
     for line in lines {
         let l = line.unwrap();
         grid.push(l.chars().map(|c| c.to_digit(10).unwrap()).collect());
-        visibility.push(l.chars().map(|_| false).collect());
-    }
-    let xsize = grid[0].len();
-    let ysize = grid.len();
-
-    for x in 0..xsize {
-        bfs(
-            (x as i32, 0),
-            (xsize, ysize),
-            (0, 1),
-            &grid,
-            &mut visibility,
-        );
-        bfs(
-            (x as i32, (ysize as i32) - 1),
-            (xsize, ysize),
-            (0, -1),
-            &grid,
-            &mut visibility,
-        );
-    }
-    for y in 0..ysize {
-        bfs(
-            (0, y as i32),
-            (xsize, ysize),
-            (1, 0),
-            &grid,
-            &mut visibility,
-        );
-        bfs(
-            ((xsize as i32) - 1, y as i32),
-            (xsize, ysize),
-            (-1, 0),
-            &grid,
-            &mut visibility,
-        );
     }
 
-    let cnt: u32 = visibility
-        .iter()
-        .map(|x| x.iter().map(|x| *x as u32).sum::<u32>())
-        .sum();
+    let forest = analyse(&grid);
+
+    let cnt = forest.visible.iter().flatten().filter(|v| **v).count();
     println!("Day 8, part 1 {}", cnt);
 
-    let mut max_score = 0;
-    for x in 0..xsize {
-        for y in 0..ysize {
-            max_score = std::cmp::max(
-                max_score,
-                bfs_2((x as i32, y as i32), (xsize, ysize), &grid),
-            );
-        }
-    }
+    let max_score = forest.scenic_scores.iter().flatten().max().unwrap();
     println!("Day 8, part 2 {}", max_score);
 }
@@ -7,6 +7,10 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
 }
 
 #[derive(Debug)]
@@ -21,6 +25,43 @@ pub fn move_head(pos: (i32, i32), head_move: &HeadMove) -> (i32, i32) {
         Direction::Down => (pos.0, pos.1 - 1),
         Direction::Right => (pos.0 + 1, pos.1),
         Direction::Left => (pos.0 - 1, pos.1),
+        Direction::UpLeft => (pos.0 - 1, pos.1 + 1),
+        Direction::UpRight => (pos.0 + 1, pos.1 + 1),
+        Direction::DownLeft => (pos.0 - 1, pos.1 - 1),
+        Direction::DownRight => (pos.0 + 1, pos.1 - 1),
+    }
+}
+
+/// How a knot follows the knot in front of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowRule {
+    /// Stays touching, moving one king step when it falls behind, as in the puzzle.
+    King,
+    /// Lets the rope go slack by up to `slack` cells in each axis before
+    /// moving one king step.
+    Elastic(u32),
+}
+
+impl FollowRule {
+    fn slack(self) -> u32 {
+        match self {
+            FollowRule::King => 1,
+            FollowRule::Elastic(slack) => slack,
+        }
+    }
+
+    /// ```
+    /// use aoc::day9::FollowRule;
+    /// assert_eq!(FollowRule::King.follow((2, 1), (0, 0)), (1, 1));
+    /// assert_eq!(FollowRule::Elastic(2).follow((2, 1), (0, 0)), (0, 0));
+    /// assert_eq!(FollowRule::Elastic(2).follow((3, -3), (0, 0)), (1, -1));
+    /// ```
+    pub fn follow(self, leader: (i32, i32), knot: (i32, i32)) -> (i32, i32) {
+        let (dx, dy) = (leader.0 - knot.0, leader.1 - knot.1);
+        if dx.unsigned_abs().max(dy.unsigned_abs()) <= self.slack() {
+            return knot;
+        }
+        (knot.0 + dx.signum(), knot.1 + dy.signum())
     }
 }
 
@@ -28,31 +69,130 @@ pub fn move_head(pos: (i32, i32), head_move: &HeadMove) -> (i32, i32) {
 /// use aoc::day9::find_tail_position;
 /// assert_eq!(find_tail_position((1, 2), (0, 0)), (1, 1));
 /// assert_eq!(find_tail_position((2, 1), (0, 0)), (1, 1));
+/// ```
 pub fn find_tail_position(head_pos: (i32, i32), tail_pos: (i32, i32)) -> (i32, i32) {
-    if (head_pos.0 - tail_pos.0).abs() <= 1 && (head_pos.1 - tail_pos.1).abs() <= 1 {
-        return tail_pos;
+    FollowRule::King.follow(head_pos, tail_pos)
+}
+
+/// A rope of knots, the first being the head, that remembers every position
+/// each knot has visited.
+pub struct Rope {
+    rule: FollowRule,
+    knots: Vec<(i32, i32)>,
+    visited: Vec<HashSet<(i32, i32)>>,
+    /// Every position of each knot in order, starting at the origin.
+    trails: Vec<Vec<(i32, i32)>>,
+}
+
+impl Rope {
+    /// A rope with `knot_count` knots, head included, all starting at the origin.
+    pub fn new(knot_count: usize) -> Rope {
+        Rope::with_rule(knot_count, FollowRule::King)
+    }
+
+    pub fn with_rule(knot_count: usize, rule: FollowRule) -> Rope {
+        assert!(knot_count > 0, "A rope needs at least one knot");
+        Rope {
+            rule,
+            knots: vec![(0, 0); knot_count],
+            visited: vec![HashSet::from([(0, 0)]); knot_count],
+            trails: vec![vec![(0, 0)]; knot_count],
+        }
+    }
+
+    /// Moves the head one step at a time, each knot following the one before it.
+    /// ```
+    /// use aoc::day9::{Direction, HeadMove, Rope};
+    /// let mut rope = Rope::new(3);
+    /// rope.apply(&HeadMove{direction: Direction::Right, distance: 4});
+    /// assert_eq!(rope.knots(), &[(4, 0), (3, 0), (2, 0)]);
+    /// assert_eq!(rope.visited(0).len(), 5);
+    /// assert_eq!(rope.visited(2).len(), 3);
+    /// ```
+    pub fn apply(&mut self, head_move: &HeadMove) {
+        for _ in 0..head_move.distance {
+            self.knots[0] = move_head(self.knots[0], head_move);
+            self.visited[0].insert(self.knots[0]);
+            self.trails[0].push(self.knots[0]);
+            for idx in 1..self.knots.len() {
+                let knot = self.rule.follow(self.knots[idx - 1], self.knots[idx]);
+                if knot == self.knots[idx] {
+                    // Knots further down the rope cannot move either.
+                    break;
+                }
+                self.knots[idx] = knot;
+                self.visited[idx].insert(knot);
+                self.trails[idx].push(knot);
+            }
+        }
+    }
+
+    pub fn knots(&self) -> &[(i32, i32)] {
+        &self.knots
+    }
+
+    /// Positions visited by the knot at index `knot`, 0 being the head.
+    pub fn visited(&self, knot: usize) -> &HashSet<(i32, i32)> {
+        &self.visited[knot]
+    }
+
+    /// Successive positions of the knot at index `knot`, starting at the origin.
+    pub fn trail(&self, knot: usize) -> &[(i32, i32)] {
+        &self.trails[knot]
+    }
+
+    /// Draws every knot's trail as a polyline in its own colour, over the
+    /// bounding box of all visited cells. Up is towards the top of the image.
+    /// ```
+    /// use aoc::day9::{Direction, HeadMove, Rope};
+    /// let mut rope = Rope::new(2);
+    /// rope.apply(&HeadMove{direction: Direction::UpRight, distance: 2});
+    /// let svg = rope.to_svg(10);
+    /// assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"30\" height=\"30\""));
+    /// assert!(svg.contains("<polyline points=\"5,25 15,15 25,5\""));
+    /// assert_eq!(svg.matches("<polyline").count(), 2);
+    /// ```
+    pub fn to_svg(&self, cell_size: u32) -> String {
+        let points = self.trails.iter().flatten();
+        let min_x = points.clone().map(|p| p.0).min().unwrap();
+        let max_x = points.clone().map(|p| p.0).max().unwrap();
+        let min_y = points.clone().map(|p| p.1).min().unwrap();
+        let max_y = points.map(|p| p.1).max().unwrap();
+        let cell = cell_size as i64;
+        let width = (max_x - min_x + 1) as i64 * cell;
+        let height = (max_y - min_y + 1) as i64 * cell;
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            width, height, width, height
+        );
+        svg += &format!(
+            "  <rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+            width, height
+        );
+        // Tail first, so the knots closer to the head are drawn on top.
+        for (idx, trail) in self.trails.iter().enumerate().rev() {
+            let points: Vec<String> = trail
+                .iter()
+                .map(|&(x, y)| {
+                    format!(
+                        "{},{}",
+                        (x - min_x) as i64 * cell + cell / 2,
+                        (max_y - y) as i64 * cell + cell / 2
+                    )
+                })
+                .collect();
+            let hue = 360 * idx / self.trails.len();
+            svg += &format!(
+                "  <polyline points=\"{}\" fill=\"none\" stroke=\"hsl({}, 80%, 45%)\" stroke-width=\"{}\"/>\n",
+                points.join(" "),
+                hue,
+                (cell_size / 5).max(1)
+            );
+        }
+        svg += "</svg>\n";
+        svg
     }
-    [
-        (1, 1),
-        (1, -1),
-        (-1, -1),
-        (-1, 1),
-        (0, 1),
-        (1, 0),
-        (0, -1),
-        (-1, 0),
-    ]
-    .iter()
-    .map(|step| {
-        let new_tail_pos = (tail_pos.0 + step.0, tail_pos.1 + step.1);
-        let head_dist = (head_pos.0 - new_tail_pos.0) * (head_pos.0 - new_tail_pos.0)
-            + (head_pos.1 - new_tail_pos.1) * (head_pos.1 - new_tail_pos.1);
-        (head_dist, new_tail_pos)
-    })
-    .filter(|x| x.0 != 0)
-    .min()
-    .unwrap()
-    .1
 }
 
 pub fn run() {
@@ -67,32 +207,20 @@ pub fn run() {
                 ("D", let distance: u32) => HeadMove{direction: Direction::Down, distance},
                 ("L", let distance: u32) => HeadMove{direction: Direction::Left, distance},
                 ("R", let distance: u32) => HeadMove{direction: Direction::Right, distance},
+                ("UL", let distance: u32) => HeadMove{direction: Direction::UpLeft, distance},
+                ("UR", let distance: u32) => HeadMove{direction: Direction::UpRight, distance},
+                ("DL", let distance: u32) => HeadMove{direction: Direction::DownLeft, distance},
+                ("DR", let distance: u32) => HeadMove{direction: Direction::DownRight, distance},
             )
             .unwrap(),
         );
     }
 
-    let mut head_position = (0, 0);
-    let mut tail_position = vec![(0, 0); 1];
-    let mut visited_positions = HashSet::new();
-    visited_positions.insert((0, 0));
-
-    for step in steps {
-        for _ in 0..step.distance {
-            head_position = move_head(head_position, &step);
-            let mut tmp_head_position = head_position;
-            for tail_pos in &mut tail_position {
-                // let tail_pos = tail_position[idx];
-                *tail_pos = find_tail_position(tmp_head_position, *tail_pos);
-                assert!(
-                    (tmp_head_position.0 - tail_pos.0).abs() <= 1
-                        && (tmp_head_position.1 - tail_pos.1).abs() <= 1
-                );
-                tmp_head_position = *tail_pos;
-            }
-            visited_positions.insert(tmp_head_position);
-        }
+    let mut rope = Rope::new(10);
+    for step in &steps {
+        rope.apply(step);
     }
 
-    println!("Day 9, part 1 and 2: {}", visited_positions.len());
+    println!("Day 9, part 1: {}", rope.visited(1).len());
+    println!("Day 9, part 2: {}", rope.visited(9).len());
 }